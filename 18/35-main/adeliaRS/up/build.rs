@@ -0,0 +1,6 @@
+// build.rs
+
+fn main() {
+    // Embedded migrations are read at compile time; rebuild when one is added or changed
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -7,7 +7,9 @@ DB_PORT="5432"
 DB_NAME="chessdb"
 DB_USER="chess1"
 DB_PASSWORD="changeme"   # Change to a secure password in production
-ADMIN_PASSWORD="af3"      # Change to a secure password in production
+SESSION_KEY="$(openssl rand -hex 32)"   # Signs session cookies (admin logins and form tokens)
+POSTER_HASH_SALT="$(openssl rand -hex 32)"   # Salts the IP hashes stored with posts
+TRIPCODE_SECRET="$(openssl rand -hex 32)"   # Keys secure (name##secret) tripcodes
 
 # Check if .env already exists
 if [ -f .env ]; then
@@ -17,12 +19,6 @@ fi
 
 echo "=== Automated Installation Starting ==="
 
-# Ensure db.sql is present
-if [ ! -f db.sql ]; then
-    echo "db.sql file not found. Please provide a db.sql script to set up the database schema."
-    exit 1
-fi
-
 echo "Creating PostgreSQL user and database..."
 # Check if the user exists
 USER_EXISTS=$(sudo -u postgres psql -tAc "SELECT 1 FROM pg_roles WHERE rolname='${DB_USER}'")
@@ -42,16 +38,24 @@ echo "Database and user created or already exist."
 echo "Generating .env file..."
 cat > .env <<EOF
 DATABASE_URL=postgres://${DB_USER}:${DB_PASSWORD}@${DB_HOST}:${DB_PORT}/${DB_NAME}
-ADMIN_PASSWORD=${ADMIN_PASSWORD}
+SESSION_KEY=${SESSION_KEY}
+POSTER_HASH_SALT=${POSTER_HASH_SALT}
+TRIPCODE_SECRET=${TRIPCODE_SECRET}
+# Set to true when running behind a reverse proxy that sets X-Forwarded-For
+CHESS_SERVER_TRUST_PROXY_HEADERS=false
+# Set to true when the site is served over HTTPS; browsers drop secure cookies on plain HTTP
+CHESS_SERVER_COOKIE_SECURE=false
 EOF
 
 echo ".env file created."
 
-echo "Running db.sql to set up schema..."
-export PGPASSWORD="${DB_PASSWORD}"
-psql -h "${DB_HOST}" -U "${DB_USER}" -d "${DB_NAME}" -f db.sql
+if [ ! -f config.toml ]; then
+    cp config.example.toml config.toml
+    echo "config.toml created from config.example.toml; edit it to change the listen address, paths and limits."
+fi
 
-echo "Database schema and seed data loaded."
+echo "The database schema is created by the migrations in ./migrations when the server starts."
 echo "=== Installation Complete ==="
+echo "Create an admin account with 'cargo run -- admin set <username>'."
 echo "You can now run the server with 'cargo run'. The application will use the .env file for configuration."
 
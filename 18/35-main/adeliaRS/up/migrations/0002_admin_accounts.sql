@@ -0,0 +1,4 @@
+-- Admin passwords are Argon2 hashes created with `chess_board admin set <username>`.
+-- Drop the plaintext seed row that db.sql used to insert.
+
+DELETE FROM admins WHERE password_hash NOT LIKE '$argon2%';
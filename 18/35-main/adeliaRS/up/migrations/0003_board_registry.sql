@@ -0,0 +1,24 @@
+-- The boards table becomes the board registry: short URIs and descriptions.
+
+ALTER TABLE boards ADD COLUMN IF NOT EXISTS uri TEXT;
+ALTER TABLE boards ADD COLUMN IF NOT EXISTS description TEXT NOT NULL DEFAULT '';
+
+-- These three boards used to be hardcoded in board.rs. Keep any name an admin already set.
+INSERT INTO boards (id, uri, name) VALUES
+    (1, 'kg', 'Kings Gambit'),
+    (2, 'qg', 'Queens Gambit'),
+    (3, 'op', 'Openings')
+ON CONFLICT (id) DO UPDATE
+    SET uri = EXCLUDED.uri, name = EXCLUDED.name
+    WHERE boards.uri IS NULL AND boards.name = 'Board ' || boards.id;
+
+-- db.sql seeded 'Board 4'..'Board 100', which were never shown because board.rs
+-- did not list them. Keep them hidden unless they somehow hold threads.
+UPDATE boards SET deleted = TRUE
+WHERE uri IS NULL
+  AND name = 'Board ' || id
+  AND NOT EXISTS (SELECT 1 FROM threads WHERE threads.board_id = boards.id);
+
+UPDATE boards SET uri = id::text WHERE uri IS NULL;
+ALTER TABLE boards ALTER COLUMN uri SET NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS boards_uri_key ON boards (uri);
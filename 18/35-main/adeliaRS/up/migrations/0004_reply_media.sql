@@ -0,0 +1,4 @@
+-- Replies can carry an image or video like thread OPs.
+
+ALTER TABLE replies ADD COLUMN IF NOT EXISTS media_url TEXT;
+ALTER TABLE replies ADD COLUMN IF NOT EXISTS media_type TEXT;
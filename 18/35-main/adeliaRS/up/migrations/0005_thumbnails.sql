@@ -0,0 +1,5 @@
+-- Thumbnail URLs for uploaded images.
+-- Existing uploads can be thumbnailed with `chess_board thumbs backfill`.
+
+ALTER TABLE threads ADD COLUMN IF NOT EXISTS thumb_url TEXT;
+ALTER TABLE replies ADD COLUMN IF NOT EXISTS thumb_url TEXT;
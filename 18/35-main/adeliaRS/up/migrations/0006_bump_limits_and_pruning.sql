@@ -0,0 +1,10 @@
+-- Per-board bump limit and thread cap, and an archived state for pruned threads.
+
+ALTER TABLE boards ADD COLUMN IF NOT EXISTS bump_limit INT NOT NULL DEFAULT 300;
+ALTER TABLE boards ADD COLUMN IF NOT EXISTS max_threads INT NOT NULL DEFAULT 150;
+ALTER TABLE boards ADD COLUMN IF NOT EXISTS archive_pruned BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE threads ADD COLUMN IF NOT EXISTS archived BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX IF NOT EXISTS threads_board_order_idx
+    ON threads (board_id, archived, pinned DESC, last_updated DESC);
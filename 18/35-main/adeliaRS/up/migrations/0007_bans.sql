@@ -0,0 +1,21 @@
+-- Poster identity for moderation, and bans by IP range or poster hash.
+
+-- Salted SHA-256 of the client IP; the raw address is never stored with posts
+ALTER TABLE threads ADD COLUMN IF NOT EXISTS poster_hash TEXT;
+ALTER TABLE replies ADD COLUMN IF NOT EXISTS poster_hash TEXT;
+
+CREATE TABLE IF NOT EXISTS bans (
+    id SERIAL PRIMARY KEY,
+    ip_range CIDR,
+    poster_hash TEXT,
+    -- NULL bans from every board
+    board_id INT REFERENCES boards(id) ON DELETE CASCADE,
+    reason TEXT NOT NULL DEFAULT '',
+    created_by TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    -- NULL never expires
+    expires_at BIGINT,
+    CHECK (ip_range IS NOT NULL OR poster_hash IS NOT NULL)
+);
+
+CREATE INDEX IF NOT EXISTS bans_poster_hash_idx ON bans (poster_hash);
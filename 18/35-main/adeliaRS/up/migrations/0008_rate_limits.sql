@@ -0,0 +1,18 @@
+-- Per-board posting cooldowns, and post times for duplicate-message detection.
+
+ALTER TABLE boards ADD COLUMN IF NOT EXISTS thread_cooldown INT NOT NULL DEFAULT 60;
+ALTER TABLE boards ADD COLUMN IF NOT EXISTS reply_cooldown INT NOT NULL DEFAULT 10;
+
+-- Existing posts were never timestamped; their thread's last bump is the closest record
+ALTER TABLE threads ADD COLUMN IF NOT EXISTS created_at BIGINT;
+UPDATE threads SET created_at = last_updated WHERE created_at IS NULL;
+ALTER TABLE threads ALTER COLUMN created_at SET NOT NULL;
+
+ALTER TABLE replies ADD COLUMN IF NOT EXISTS created_at BIGINT;
+UPDATE replies SET created_at = threads.last_updated
+    FROM threads
+    WHERE threads.id = replies.thread_id AND replies.created_at IS NULL;
+ALTER TABLE replies ALTER COLUMN created_at SET NOT NULL;
+
+CREATE INDEX IF NOT EXISTS threads_created_at_idx ON threads (created_at);
+CREATE INDEX IF NOT EXISTS replies_created_at_idx ON replies (created_at);
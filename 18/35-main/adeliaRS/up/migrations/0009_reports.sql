@@ -0,0 +1,18 @@
+-- User reports of threads and replies, queued for admins.
+
+CREATE TABLE IF NOT EXISTS reports (
+    id SERIAL PRIMARY KEY,
+    -- Exactly one of thread_id / reply_id is set; reports go away with the post
+    thread_id INT REFERENCES threads(id) ON DELETE CASCADE,
+    reply_id INT REFERENCES replies(id) ON DELETE CASCADE,
+    reason TEXT NOT NULL,
+    -- Poster hash of the reporter, so each client reports a post only once
+    reporter_hash TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    CHECK ((thread_id IS NULL) <> (reply_id IS NULL))
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS reports_thread_reporter_idx
+    ON reports (thread_id, reporter_hash) WHERE thread_id IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS reports_reply_reporter_idx
+    ON reports (reply_id, reporter_hash) WHERE reply_id IS NOT NULL;
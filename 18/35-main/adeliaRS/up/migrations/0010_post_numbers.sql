@@ -0,0 +1,32 @@
+-- One post number sequence per board, shared by threads and replies, used for >>N links.
+
+ALTER TABLE boards ADD COLUMN IF NOT EXISTS post_counter INT NOT NULL DEFAULT 0;
+ALTER TABLE threads ADD COLUMN IF NOT EXISTS post_no INT;
+ALTER TABLE replies ADD COLUMN IF NOT EXISTS post_no INT;
+
+-- Number existing posts in the order they were made; a thread comes before its replies
+CREATE TEMPORARY TABLE numbered_posts ON COMMIT DROP AS
+SELECT is_reply, id, board_id,
+    ROW_NUMBER() OVER (PARTITION BY board_id ORDER BY created_at, is_reply, id)::INT AS post_no
+FROM (
+    SELECT FALSE AS is_reply, t.id, t.board_id, t.created_at FROM threads t
+    UNION ALL
+    SELECT TRUE AS is_reply, r.id, t.board_id, r.created_at
+    FROM replies r JOIN threads t ON t.id = r.thread_id
+) posts;
+
+UPDATE threads SET post_no = n.post_no
+    FROM numbered_posts n
+    WHERE NOT n.is_reply AND n.id = threads.id AND threads.post_no IS NULL;
+UPDATE replies SET post_no = n.post_no
+    FROM numbered_posts n
+    WHERE n.is_reply AND n.id = replies.id AND replies.post_no IS NULL;
+
+UPDATE boards SET post_counter = COALESCE(
+    (SELECT MAX(post_no) FROM numbered_posts WHERE numbered_posts.board_id = boards.id), 0);
+
+ALTER TABLE threads ALTER COLUMN post_no SET NOT NULL;
+ALTER TABLE replies ALTER COLUMN post_no SET NOT NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS threads_board_post_no_idx ON threads (board_id, post_no);
+CREATE INDEX IF NOT EXISTS replies_post_no_idx ON replies (post_no);
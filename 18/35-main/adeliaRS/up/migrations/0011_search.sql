@@ -0,0 +1,14 @@
+-- Full-text search over thread titles and messages and reply messages.
+-- Titles carry more weight than message text when ranking results.
+
+ALTER TABLE threads ADD COLUMN IF NOT EXISTS search_vector TSVECTOR
+    GENERATED ALWAYS AS (
+        setweight(to_tsvector('english', title), 'A') ||
+        setweight(to_tsvector('english', message), 'B')
+    ) STORED;
+
+ALTER TABLE replies ADD COLUMN IF NOT EXISTS search_vector TSVECTOR
+    GENERATED ALWAYS AS (setweight(to_tsvector('english', message), 'B')) STORED;
+
+CREATE INDEX IF NOT EXISTS threads_search_idx ON threads USING GIN (search_vector);
+CREATE INDEX IF NOT EXISTS replies_search_idx ON replies USING GIN (search_vector);
@@ -0,0 +1,9 @@
+-- Size and dimensions of uploaded media, shown in the post header.
+-- Earlier uploads can be filled in with `chess_board media details`.
+
+ALTER TABLE threads ADD COLUMN IF NOT EXISTS media_width INTEGER;
+ALTER TABLE threads ADD COLUMN IF NOT EXISTS media_height INTEGER;
+ALTER TABLE threads ADD COLUMN IF NOT EXISTS media_size BIGINT;
+ALTER TABLE replies ADD COLUMN IF NOT EXISTS media_width INTEGER;
+ALTER TABLE replies ADD COLUMN IF NOT EXISTS media_height INTEGER;
+ALTER TABLE replies ADD COLUMN IF NOT EXISTS media_size BIGINT;
@@ -0,0 +1,18 @@
+-- SHA-256 of each upload, used to store identical files once, to enforce the
+-- per-board repost rule and to refuse files on the admin blocklist.
+
+ALTER TABLE threads ADD COLUMN IF NOT EXISTS media_hash TEXT;
+ALTER TABLE replies ADD COLUMN IF NOT EXISTS media_hash TEXT;
+CREATE INDEX IF NOT EXISTS threads_media_hash_idx ON threads (media_hash);
+CREATE INDEX IF NOT EXISTS replies_media_hash_idx ON replies (media_hash);
+
+-- Days before the same file may be posted on the board again; 0 allows reposts
+ALTER TABLE boards ADD COLUMN IF NOT EXISTS repost_days INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS banned_files (
+    id SERIAL PRIMARY KEY,
+    sha256 TEXT NOT NULL UNIQUE,
+    reason TEXT NOT NULL DEFAULT '',
+    created_by TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
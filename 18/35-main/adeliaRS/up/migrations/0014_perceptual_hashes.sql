@@ -0,0 +1,13 @@
+-- Perceptual hash (dHash) of uploaded images, and the admin blocklist of images
+-- matched by it, so resized or recompressed copies of a banned image are refused too.
+
+ALTER TABLE threads ADD COLUMN IF NOT EXISTS media_phash BIGINT;
+ALTER TABLE replies ADD COLUMN IF NOT EXISTS media_phash BIGINT;
+
+CREATE TABLE IF NOT EXISTS banned_images (
+    id SERIAL PRIMARY KEY,
+    phash BIGINT NOT NULL,
+    reason TEXT NOT NULL DEFAULT '',
+    created_by TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
@@ -0,0 +1,6 @@
+-- Optional poster name and tripcode on threads and replies. NULL shows as Anonymous.
+
+ALTER TABLE threads ADD COLUMN IF NOT EXISTS name TEXT;
+ALTER TABLE threads ADD COLUMN IF NOT EXISTS tripcode TEXT;
+ALTER TABLE replies ADD COLUMN IF NOT EXISTS name TEXT;
+ALTER TABLE replies ADD COLUMN IF NOT EXISTS tripcode TEXT;
@@ -0,0 +1,10 @@
+-- Board ids come from a sequence, so boards created at the same time cannot take the
+-- same id. Existing boards were numbered by hand; the sequence starts after them.
+
+CREATE SEQUENCE IF NOT EXISTS boards_id_seq OWNED BY boards.id;
+SELECT setval(
+    'boards_id_seq',
+    GREATEST((SELECT MAX(id) FROM boards), 1),
+    (SELECT MAX(id) FROM boards) IS NOT NULL
+);
+ALTER TABLE boards ALTER COLUMN id SET DEFAULT nextval('boards_id_seq');
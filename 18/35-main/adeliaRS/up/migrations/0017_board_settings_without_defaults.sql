@@ -0,0 +1,11 @@
+-- New boards take their settings from [board_defaults] in the config, which fills in
+-- the admin form. The column defaults only served to fill in the boards that existed
+-- when each column was added, so they are dropped and the config is the one source.
+
+ALTER TABLE boards
+    ALTER COLUMN bump_limit DROP DEFAULT,
+    ALTER COLUMN max_threads DROP DEFAULT,
+    ALTER COLUMN archive_pruned DROP DEFAULT,
+    ALTER COLUMN thread_cooldown DROP DEFAULT,
+    ALTER COLUMN reply_cooldown DROP DEFAULT,
+    ALTER COLUMN repost_days DROP DEFAULT;
@@ -0,0 +1,165 @@
+// src/api.rs
+//
+// Read-only JSON mirror of the public HTML routes.
+
+use crate::board::{Board, BoardRegistry};
+use crate::{clamp_page, threads_per_page, PaginationParams, Reply, Thread, REPLY_COLUMNS, THREAD_COLUMNS};
+use actix_web::{web, Error, HttpResponse};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::{Pool, Postgres};
+
+/// A thread as listed on a board index or catalog, with its reply count.
+#[derive(Serialize, sqlx::FromRow)]
+struct ThreadPreview {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    thread: Thread,
+    reply_count: i64,
+}
+
+#[derive(Serialize)]
+struct Pagination {
+    page: i32,
+    total_pages: i32,
+    per_page: i32,
+    total_threads: i64,
+}
+
+#[derive(Serialize)]
+struct BoardPage {
+    board: Board,
+    pagination: Pagination,
+    threads: Vec<ThreadPreview>,
+}
+
+#[derive(Serialize)]
+struct Catalog {
+    board: Board,
+    threads: Vec<ThreadPreview>,
+}
+
+#[derive(Serialize)]
+struct ThreadPage {
+    board: Board,
+    thread: Thread,
+    replies: Vec<Reply>,
+}
+
+fn not_found(message: &str) -> HttpResponse {
+    HttpResponse::NotFound().json(json!({ "error": message }))
+}
+
+fn preview_query(tail: &str) -> String {
+    format!(
+        r#"SELECT {}, (SELECT COUNT(*) FROM replies WHERE replies.thread_id = threads.id) AS reply_count
+        FROM threads
+        WHERE board_id = $1 AND NOT archived
+        ORDER BY pinned DESC, last_updated DESC
+        {}"#,
+        THREAD_COLUMNS, tail
+    )
+}
+
+// GET /api/boards
+pub async fn boards(registry: web::Data<BoardRegistry>) -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "boards": registry.live() }))
+}
+
+// GET /api/board/{id}?page=N
+pub async fn board_threads(
+    pool: web::Data<Pool<Postgres>>,
+    registry: web::Data<BoardRegistry>,
+    path: web::Path<i32>,
+    query: web::Query<PaginationParams>,
+) -> Result<HttpResponse, Error> {
+    let board = match registry.get(path.into_inner()) {
+        Some(board) => board,
+        None => return Ok(not_found("Board not found")),
+    };
+
+    let total_threads: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM threads WHERE board_id = $1 AND NOT archived")
+            .bind(board.id)
+            .fetch_one(pool.get_ref())
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let (page, total_pages) = clamp_page(query.page, total_threads);
+
+    let threads = sqlx::query_as::<_, ThreadPreview>(&preview_query("LIMIT $2 OFFSET $3"))
+        .bind(board.id)
+        .bind(threads_per_page())
+        .bind((page - 1) * threads_per_page())
+        .fetch_all(pool.get_ref())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(BoardPage {
+        board,
+        pagination: Pagination {
+            page,
+            total_pages,
+            per_page: threads_per_page(),
+            total_threads,
+        },
+        threads,
+    }))
+}
+
+// GET /api/board/{id}/catalog: every live thread on the board in one response
+pub async fn catalog(
+    pool: web::Data<Pool<Postgres>>,
+    registry: web::Data<BoardRegistry>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    let board = match registry.get(path.into_inner()) {
+        Some(board) => board,
+        None => return Ok(not_found("Board not found")),
+    };
+
+    let threads = sqlx::query_as::<_, ThreadPreview>(&preview_query(""))
+        .bind(board.id)
+        .fetch_all(pool.get_ref())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(Catalog { board, threads }))
+}
+
+// GET /api/thread/{id}
+pub async fn thread(
+    pool: web::Data<Pool<Postgres>>,
+    registry: web::Data<BoardRegistry>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    let thread: Option<Thread> = sqlx::query_as(&format!(
+        "SELECT {} FROM threads WHERE id = $1",
+        THREAD_COLUMNS
+    ))
+    .bind(path.into_inner())
+    .fetch_optional(pool.get_ref())
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    // Threads on deleted boards are hidden along with the board
+    let (thread, board) = match thread.and_then(|t| registry.get(t.board_id).map(|b| (t, b))) {
+        Some(found) => found,
+        None => return Ok(not_found("Thread not found")),
+    };
+
+    let replies = sqlx::query_as::<_, Reply>(&format!(
+        "SELECT {} FROM replies WHERE thread_id = $1 ORDER BY id ASC",
+        REPLY_COLUMNS
+    ))
+    .bind(thread.id)
+    .fetch_all(pool.get_ref())
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(ThreadPage {
+        board,
+        thread,
+        replies,
+    }))
+}
@@ -0,0 +1,175 @@
+// src/auth.rs
+
+use actix_session::{Session, SessionExt, SessionInsertError};
+use actix_web::{
+    dev::Payload, error::InternalError, http::header::LOCATION, http::Method, web, Error,
+    FromRequest, HttpRequest, HttpResponse,
+};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use futures_util::future::LocalBoxFuture;
+use sqlx::{Pool, Postgres};
+use std::sync::OnceLock;
+
+const SESSION_ADMIN_KEY: &str = "admin";
+
+/// Hashes a password with Argon2id and a random salt, returning the PHC string
+/// that gets stored in `admins.password_hash`.
+pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+}
+
+/// Checks a password against a stored PHC string.
+/// Anything that is not a valid Argon2 hash (e.g. an old plaintext seed row) never matches.
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    match PasswordHash::new(password_hash) {
+        Ok(parsed) => Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Hash of a random password, checked when a login names no usable account so that it
+/// takes as long as a wrong password and does not reveal which usernames exist.
+fn dummy_hash() -> &'static str {
+    static DUMMY: OnceLock<String> = OnceLock::new();
+    DUMMY.get_or_init(|| {
+        let password = SaltString::generate(&mut OsRng);
+        hash_password(password.as_str()).unwrap_or_default()
+    })
+}
+
+/// Looks up an admin by username and verifies the password.
+pub async fn authenticate(
+    pool: &Pool<Postgres>,
+    username: &str,
+    password: &str,
+) -> Result<bool, sqlx::Error> {
+    let stored: Option<String> =
+        sqlx::query_scalar("SELECT password_hash FROM admins WHERE username = $1")
+            .bind(username)
+            .fetch_optional(pool)
+            .await?;
+
+    match stored.filter(|hash| PasswordHash::new(hash).is_ok()) {
+        Some(hash) => Ok(verify_password(password, &hash)),
+        None => {
+            verify_password(password, dummy_hash());
+            Ok(false)
+        }
+    }
+}
+
+/// Creates an admin or replaces the password of an existing one.
+pub async fn set_admin(
+    pool: &Pool<Postgres>,
+    username: &str,
+    password: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let password_hash = hash_password(password).map_err(|e| e.to_string())?;
+    sqlx::query(
+        r#"INSERT INTO admins (username, password_hash) VALUES ($1, $2)
+        ON CONFLICT (username) DO UPDATE SET password_hash = EXCLUDED.password_hash"#,
+    )
+    .bind(username)
+    .bind(password_hash)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Logs warnings at startup about admin rows that can never log in.
+pub async fn check_admin_accounts(pool: &Pool<Postgres>) -> Result<(), sqlx::Error> {
+    let admins: Vec<(String, String)> =
+        sqlx::query_as("SELECT username, password_hash FROM admins ORDER BY username")
+            .fetch_all(pool)
+            .await?;
+
+    if admins.is_empty() {
+        log::warn!("No admin accounts exist. Create one with `chess_board admin set <username>`.");
+    }
+    for (username, password_hash) in &admins {
+        if PasswordHash::new(password_hash).is_err() {
+            log::warn!(
+                "Admin '{}' does not have an Argon2 password hash and cannot log in. \
+                 Rotate it with `chess_board admin set {}`.",
+                username,
+                username
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Marks the session as logged in for `username`, rotating the session cookie.
+pub fn login(session: &Session, username: &str) -> Result<(), SessionInsertError> {
+    session.renew();
+    session.insert(SESSION_ADMIN_KEY, username)
+}
+
+pub fn logout(session: &Session) {
+    session.purge();
+}
+
+/// Extractor for handlers that require a logged-in admin.
+/// Requests without a valid admin session are redirected to the login page.
+pub struct AdminUser {
+    pub username: String,
+}
+
+impl FromRequest for AdminUser {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let session = req.get_session();
+        let pool = req.app_data::<web::Data<Pool<Postgres>>>().cloned();
+        // Pages keep their query string; form posts are sent back to their page instead
+        let next = match req.uri().path_and_query() {
+            Some(target) if req.method() == Method::GET => target.as_str().to_string(),
+            _ => req.uri().path().to_string(),
+        };
+
+        Box::pin(async move {
+            let username = session.get::<String>(SESSION_ADMIN_KEY).ok().flatten();
+
+            // Make sure the account still exists, so deleted admins lose access immediately
+            if let (Some(username), Some(pool)) = (username, pool) {
+                let exists: Option<i32> =
+                    sqlx::query_scalar("SELECT 1 FROM admins WHERE username = $1")
+                        .bind(&username)
+                        .fetch_optional(pool.get_ref())
+                        .await
+                        .map_err(actix_web::error::ErrorInternalServerError)?;
+                if exists.is_some() {
+                    return Ok(AdminUser { username });
+                }
+                session.purge();
+            }
+
+            let redirect = HttpResponse::SeeOther()
+                .insert_header((LOCATION, login_url(&next)))
+                .finish();
+            Err(InternalError::from_response("Login required", redirect).into())
+        })
+    }
+}
+
+/// Login page that returns to `next` afterwards.
+pub fn login_url(next: &str) -> String {
+    format!("/admin/login?next={}", urlencoding::encode(next))
+}
+
+/// Only allow redirects back into the admin area after logging in.
+pub fn safe_next(next: Option<&str>) -> String {
+    match next {
+        Some(path) if path.starts_with("/admin/") && !path.starts_with("//") => path.to_string(),
+        _ => "/admin".to_string(),
+    }
+}
@@ -0,0 +1,185 @@
+// src/bans.rs
+
+use crate::board::BoardRegistry;
+use crate::poster::Poster;
+use crate::{escape_html, format_timestamp};
+use actix_web::HttpResponse;
+use chrono::Utc;
+use sqlx::{Pool, Postgres};
+use std::net::IpAddr;
+
+const BAN_COLUMNS: &str =
+    "id, ip_range::TEXT AS ip_range, poster_hash, board_id, reason, created_by, created_at, expires_at";
+
+#[derive(sqlx::FromRow)]
+pub struct Ban {
+    pub id: i32,
+    pub ip_range: Option<String>,
+    pub poster_hash: Option<String>,
+    pub board_id: Option<i32>,
+    pub reason: String,
+    pub created_by: String,
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+}
+
+/// Returns the ban that stops `poster` from posting on `board_id`, if any.
+/// A permanent ban is preferred over one that expires, so the page shows the worst case.
+pub async fn find_active(
+    pool: &Pool<Postgres>,
+    poster: &Poster,
+    board_id: i32,
+) -> Result<Option<Ban>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        r#"SELECT {} FROM bans
+        WHERE (expires_at IS NULL OR expires_at > $1)
+          AND (board_id IS NULL OR board_id = $2)
+          AND (($3::INET <<= ip_range) OR poster_hash = $4)
+        ORDER BY expires_at DESC NULLS FIRST
+        LIMIT 1"#,
+        BAN_COLUMNS
+    ))
+    .bind(Utc::now().timestamp())
+    .bind(board_id)
+    .bind(poster.ip.to_string())
+    .bind(&poster.hash)
+    .fetch_optional(pool)
+    .await
+}
+
+/// All bans that have not expired yet, newest first.
+pub async fn list_active(pool: &Pool<Postgres>) -> Result<Vec<Ban>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        r#"SELECT {} FROM bans
+        WHERE expires_at IS NULL OR expires_at > $1
+        ORDER BY created_at DESC, id DESC"#,
+        BAN_COLUMNS
+    ))
+    .bind(Utc::now().timestamp())
+    .fetch_all(pool)
+    .await
+}
+
+/// Checks an address or CIDR range typed by an admin, e.g. `203.0.113.7` or `2001:db8::/32`.
+/// Returns it in the form Postgres expects, or `None` if it is not valid. Bits past the
+/// prefix are cleared, so `203.0.113.7/24` bans `203.0.113.0/24`.
+pub fn parse_ip_range(input: &str) -> Option<String> {
+    let input = input.trim();
+    let (addr, prefix) = match input.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (input, None),
+    };
+    let addr: IpAddr = addr.parse().ok()?;
+    let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(prefix) => prefix.parse::<u8>().ok().filter(|p| *p <= max_prefix)?,
+        None => max_prefix,
+    };
+    let network = match addr {
+        IpAddr::V4(v4) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            IpAddr::V4((u32::from(v4) & mask).into())
+        }
+        IpAddr::V6(v6) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            IpAddr::V6((u128::from(v6) & mask).into())
+        }
+    };
+    Some(format!("{}/{}", network, prefix))
+}
+
+/// Human-readable board scope of a ban.
+pub fn scope_label(ban: &Ban, registry: &BoardRegistry) -> String {
+    match ban.board_id {
+        None => "all boards".to_string(),
+        Some(board_id) => match registry.all().into_iter().find(|b| b.id == board_id) {
+            Some(board) => format!("/{}/", board.uri),
+            None => format!("board {}", board_id),
+        },
+    }
+}
+
+pub fn expiry_label(ban: &Ban) -> String {
+    match ban.expires_at {
+        Some(expires_at) => format_timestamp(expires_at),
+        None => "never".to_string(),
+    }
+}
+
+/// Page shown instead of accepting a post from a banned poster.
+pub fn banned_response(ban: &Ban, registry: &BoardRegistry) -> HttpResponse {
+    let reason = if ban.reason.is_empty() {
+        "No reason given."
+    } else {
+        ban.reason.as_str()
+    };
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Banned</title>
+    <link rel="stylesheet" href="/static/style.css">
+</head>
+<body>
+    <h1>You are banned</h1>
+    <p>You are banned from posting on {}.</p>
+    <p>Reason: {}</p>
+    <p>Expires: {}</p>
+    <p>Ban ID: {}</p>
+    <p><a href="/">[Home]</a></p>
+</body>
+</html>"#,
+        escape_html(&scope_label(ban, registry)),
+        escape_html(reason),
+        expiry_label(ban),
+        ban.id
+    );
+    HttpResponse::Forbidden().content_type("text/html").body(html)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_addresses_get_a_full_prefix() {
+        assert_eq!(parse_ip_range("203.0.113.7").as_deref(), Some("203.0.113.7/32"));
+        assert_eq!(parse_ip_range(" 2001:db8::1 ").as_deref(), Some("2001:db8::1/128"));
+    }
+
+    #[test]
+    fn ranges_keep_their_prefix() {
+        assert_eq!(parse_ip_range("203.0.113.0/24").as_deref(), Some("203.0.113.0/24"));
+        assert_eq!(parse_ip_range("2001:db8::/32").as_deref(), Some("2001:db8::/32"));
+        assert_eq!(parse_ip_range("0.0.0.0/0").as_deref(), Some("0.0.0.0/0"));
+        assert_eq!(parse_ip_range("::/0").as_deref(), Some("::/0"));
+    }
+
+    #[test]
+    fn host_bits_are_cleared() {
+        // Postgres refuses a CIDR value with bits set past its prefix
+        assert_eq!(parse_ip_range("203.0.113.7/24").as_deref(), Some("203.0.113.0/24"));
+        assert_eq!(parse_ip_range("198.51.100.255/25").as_deref(), Some("198.51.100.128/25"));
+        assert_eq!(parse_ip_range("10.1.2.3/0").as_deref(), Some("0.0.0.0/0"));
+        assert_eq!(parse_ip_range("2001:db8:abcd::1/48").as_deref(), Some("2001:db8:abcd::/48"));
+    }
+
+    #[test]
+    fn invalid_input_is_refused() {
+        for input in [
+            "",
+            "203.0.113",
+            "203.0.113.256",
+            "203.0.113.0/33",
+            "2001:db8::/129",
+            "203.0.113.0/-1",
+            "203.0.113.0/",
+            "203.0.113.0/24/8",
+            "example.com",
+            "203.0.113.0 /24",
+        ] {
+            assert_eq!(parse_ip_range(input), None, "{:?}", input);
+        }
+    }
+}
@@ -1,21 +1,146 @@
 // src/board.rs
 
-pub struct BoardInfo {
+use serde::Serialize;
+use sqlx::{Pool, Postgres};
+use std::sync::RwLock;
+
+#[derive(Clone, Serialize, sqlx::FromRow)]
+pub struct Board {
     pub id: i32,
-    pub name: &'static str,
+    pub uri: String,
+    pub name: String,
+    pub description: String,
+    pub deleted: bool,
+    /// Replies after this many no longer bump the thread.
+    pub bump_limit: i32,
+    /// Live threads kept on the board; older ones are pruned.
+    pub max_threads: i32,
+    /// Archive pruned threads instead of deleting them.
+    pub archive_pruned: bool,
+    /// Seconds a poster must wait between new threads on this board.
+    pub thread_cooldown: i32,
+    /// Seconds a poster must wait between replies on this board.
+    pub reply_cooldown: i32,
+    /// Days before the same file may be posted here again; 0 allows reposts.
+    pub repost_days: i32,
 }
 
-pub const BOARDS: &[BoardInfo] = &[
-    BoardInfo { id: 1, name: "Kings Gambit" },
-    BoardInfo { id: 2, name: "Queens Gambit" },
-    BoardInfo { id: 3, name: "Openings" },
-    // Add more boards as needed
+/// URIs that would collide with the application's own top-level routes.
+const RESERVED_URIS: &[&str] = &[
+    "admin", "api", "board", "thread", "reply", "report", "search", "static", "uploads",
+    "thumbs",
 ];
 
-/// Retrieves the name of the board based on its ID.
-/// Returns `Some(&str)` if the board is defined, otherwise `None`.
-pub fn get_board_name(id: i32) -> Option<&'static str> {
-    BOARDS.iter()
-        .find(|board| board.id == id)
-        .map(|board| board.name)
+/// In-memory copy of the `boards` table.
+/// Reload it after any change to the table so new boards appear without a restart.
+pub struct BoardRegistry {
+    boards: RwLock<Vec<Board>>,
+}
+
+impl BoardRegistry {
+    pub async fn load(pool: &Pool<Postgres>) -> Result<Self, sqlx::Error> {
+        let registry = BoardRegistry {
+            boards: RwLock::new(Vec::new()),
+        };
+        registry.reload(pool).await?;
+        Ok(registry)
+    }
+
+    pub async fn reload(&self, pool: &Pool<Postgres>) -> Result<(), sqlx::Error> {
+        let boards = sqlx::query_as::<_, Board>(
+            r#"SELECT id, uri, name, description, deleted, bump_limit, max_threads, archive_pruned,
+                thread_cooldown, reply_cooldown, repost_days
+            FROM boards ORDER BY id ASC"#,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        *self.boards.write().unwrap() = boards;
+        Ok(())
+    }
+
+    /// Returns a live (not deleted) board by its ID.
+    pub fn get(&self, id: i32) -> Option<Board> {
+        self.boards
+            .read()
+            .unwrap()
+            .iter()
+            .find(|board| board.id == id && !board.deleted)
+            .cloned()
+    }
+
+    /// Returns a live (not deleted) board by its short URI, e.g. `b` for `/b/`.
+    pub fn get_by_uri(&self, uri: &str) -> Option<Board> {
+        self.boards
+            .read()
+            .unwrap()
+            .iter()
+            .find(|board| board.uri == uri && !board.deleted)
+            .cloned()
+    }
+
+    /// All live boards, ordered by ID.
+    pub fn live(&self) -> Vec<Board> {
+        self.boards
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|board| !board.deleted)
+            .cloned()
+            .collect()
+    }
+
+    /// Every board including soft-deleted ones, for the admin panel.
+    pub fn all(&self) -> Vec<Board> {
+        self.boards.read().unwrap().clone()
+    }
+}
+
+/// A board URI is 1-16 lowercase ASCII letters or digits and not a reserved route name.
+pub fn is_valid_uri(uri: &str) -> bool {
+    !uri.is_empty()
+        && uri.len() <= 16
+        && uri.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && !RESERVED_URIS.contains(&uri)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_lowercase_uris_are_valid() {
+        for uri in ["b", "kg", "chess960", "0", "abcdefghijklmnop"] {
+            assert!(is_valid_uri(uri), "{:?}", uri);
+        }
+    }
+
+    #[test]
+    fn malformed_uris_are_refused() {
+        for uri in [
+            "",
+            "abcdefghijklmnopq",
+            "KG",
+            "k g",
+            "k-g",
+            "k_g",
+            "k/g",
+            "../b",
+            "b?x=1",
+            "é",
+            "ｋｇ",
+        ] {
+            assert!(!is_valid_uri(uri), "{:?}", uri);
+        }
+    }
+
+    #[test]
+    fn reserved_route_names_are_refused() {
+        for uri in RESERVED_URIS {
+            assert!(!is_valid_uri(uri), "{:?}", uri);
+        }
+        // Only the route names themselves are reserved
+        assert!(is_valid_uri("admins"));
+        assert!(is_valid_uri("apiary"));
+    }
 }
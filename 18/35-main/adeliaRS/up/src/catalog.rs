@@ -0,0 +1,200 @@
+// src/catalog.rs
+//
+// Grid of every live thread on a board, for scanning it at a glance.
+
+use crate::board::{Board, BoardRegistry};
+use crate::{board_not_found, escape_html, render_archive_link, render_thread_badges};
+use crate::{Thread, THREAD_COLUMNS};
+use actix_web::{web, Error, HttpResponse};
+use serde::Deserialize;
+use sqlx::{Pool, Postgres};
+
+/// Characters of the title and message shown on each catalog tile.
+const TITLE_PREVIEW_LEN: usize = 50;
+const MESSAGE_PREVIEW_LEN: usize = 150;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CatalogSort {
+    Bump,
+    Created,
+    Replies,
+}
+
+impl CatalogSort {
+    const ALL: [CatalogSort; 3] = [CatalogSort::Bump, CatalogSort::Created, CatalogSort::Replies];
+
+    /// Unknown values fall back to bump order.
+    fn parse(value: Option<&str>) -> Self {
+        match value {
+            Some("created") => CatalogSort::Created,
+            Some("replies") => CatalogSort::Replies,
+            _ => CatalogSort::Bump,
+        }
+    }
+
+    fn param(self) -> &'static str {
+        match self {
+            CatalogSort::Bump => "bump",
+            CatalogSort::Created => "created",
+            CatalogSort::Replies => "replies",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            CatalogSort::Bump => "Bump order",
+            CatalogSort::Created => "Creation date",
+            CatalogSort::Replies => "Reply count",
+        }
+    }
+
+    // Pinned threads stay at the top whatever the order, as on the board index
+    fn order_by(self) -> &'static str {
+        match self {
+            CatalogSort::Bump => "pinned DESC, last_updated DESC",
+            CatalogSort::Created => "pinned DESC, created_at DESC",
+            CatalogSort::Replies => "pinned DESC, reply_count DESC, last_updated DESC",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CatalogQuery {
+    sort: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct CatalogEntry {
+    #[sqlx(flatten)]
+    thread: Thread,
+    reply_count: i64,
+    /// Images among the replies; videos and the opening post's image are not counted.
+    image_count: i64,
+}
+
+// Cuts text down to `max` characters, marking the cut with an ellipsis
+fn truncate(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((end, _)) => format!("{}…", text[..end].trim_end()),
+        None => text.to_string(),
+    }
+}
+
+fn render_thumbnail(thread: &Thread) -> String {
+    match (thread.media_url.as_deref(), thread.media_type.as_deref()) {
+        (Some(url), Some("image")) => format!(
+            r#"<img src="{}" alt="Thread image" loading="lazy">"#,
+            escape_html(thread.thumb_url.as_deref().unwrap_or(url))
+        ),
+        (Some(_), Some(_)) => r#"<span class="catalog-placeholder">Video</span>"#.to_string(),
+        _ => r#"<span class="catalog-placeholder">No image</span>"#.to_string(),
+    }
+}
+
+fn render_entry(entry: &CatalogEntry) -> String {
+    let thread = &entry.thread;
+    format!(
+        r#"<div class="catalog-thread">
+    <a class="catalog-thumb" href="/thread/{}">{}</a>
+    <div class="catalog-stats" title="Replies / Images in replies">R: {} / I: {}</div>
+    <div class="catalog-title">{}{}</div>
+    <div class="catalog-message">{}</div>
+</div>"#,
+        thread.id,
+        render_thumbnail(thread),
+        entry.reply_count,
+        entry.image_count,
+        render_thread_badges(thread),
+        escape_html(&truncate(&thread.title, TITLE_PREVIEW_LEN)),
+        escape_html(&truncate(&thread.message, MESSAGE_PREVIEW_LEN))
+    )
+}
+
+fn render_sort_links(board: &Board, current: CatalogSort) -> String {
+    CatalogSort::ALL
+        .iter()
+        .map(|sort| {
+            if *sort == current {
+                format!(r#"<span class="current">{}</span>"#, sort.label())
+            } else {
+                format!(
+                    r#"<a href="/board/{}/catalog?sort={}">{}</a>"#,
+                    board.id,
+                    sort.param(),
+                    sort.label()
+                )
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// Catalog: /board/{id}/catalog?sort=bump|created|replies
+pub async fn catalog(
+    pool: web::Data<Pool<Postgres>>,
+    registry: web::Data<BoardRegistry>,
+    path: web::Path<i32>,
+    query: web::Query<CatalogQuery>,
+) -> Result<HttpResponse, Error> {
+    let board = match registry.get(path.into_inner()) {
+        Some(board) => board,
+        None => return Ok(board_not_found()),
+    };
+    let sort = CatalogSort::parse(query.sort.as_deref());
+
+    let entries = sqlx::query_as::<_, CatalogEntry>(&format!(
+        r#"SELECT {}, counts.reply_count, counts.image_count
+        FROM threads
+        CROSS JOIN LATERAL (
+            SELECT COUNT(*) AS reply_count,
+                COUNT(*) FILTER (WHERE media_type = 'image') AS image_count
+            FROM replies WHERE replies.thread_id = threads.id
+        ) counts
+        WHERE board_id = $1 AND NOT archived
+        ORDER BY {}"#,
+        THREAD_COLUMNS,
+        sort.order_by()
+    ))
+    .bind(board.id)
+    .fetch_all(pool.get_ref())
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let grid_html = if entries.is_empty() {
+        "<p>No threads found.</p>".to_string()
+    } else {
+        format!(
+            r#"<div class="catalog">{}</div>"#,
+            entries.iter().map(render_entry).collect::<String>()
+        )
+    };
+
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>{} - Catalog</title>
+    <link rel="stylesheet" href="/static/style.css">
+</head>
+<body>
+    <div class="navigation-board">
+        <hr class="hr-green">
+        <a href="/">[Home]</a> <a href="/{}/">[Return]</a>{}
+    </div>
+    <h2>/{}/ - Catalog</h2>
+    <div class="catalog-sort">Sort by: {}</div>
+    <hr>
+    {}
+</body>
+</html>"#,
+        escape_html(&board.name),
+        escape_html(&board.uri),
+        render_archive_link(&board),
+        escape_html(&board.uri),
+        render_sort_links(&board, sort),
+        grid_html
+    );
+
+    Ok(HttpResponse::Ok().content_type("text/html").body(html))
+}
@@ -0,0 +1,203 @@
+// src/cli.rs
+
+use crate::sniff::MediaKind;
+use crate::{auth, media, metadata, upload};
+use sqlx::{Pool, Postgres};
+use std::path::Path;
+
+const USAGE: &str = "Usage:
+    chess_board                          Run the web server
+    chess_board admin set <username>     Create an admin or rotate its password
+    chess_board admin delete <username>  Remove an admin account
+    chess_board admin list               List admin accounts
+    chess_board thumbs backfill          Create missing thumbnails for uploaded images
+    chess_board media details            Record size and dimensions of earlier uploads
+    chess_board media gc [--dry-run]     Delete (or only list) uploaded files no post references";
+
+/// Runs a maintenance subcommand given on the command line instead of starting the server.
+pub async fn run(pool: &Pool<Postgres>, args: &[String]) -> std::io::Result<()> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args.as_slice() {
+        ["admin", "set", username] => admin_set(pool, username).await,
+        ["admin", "delete", username] => admin_delete(pool, username).await,
+        ["admin", "list"] => admin_list(pool).await,
+        ["thumbs", "backfill"] => thumbs_backfill(pool).await,
+        ["media", "details"] => media_details(pool).await,
+        ["media", "gc"] => media_gc(pool, false).await,
+        ["media", "gc", "--dry-run"] => media_gc(pool, true).await,
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    result.map_err(|e| std::io::Error::other(e.to_string()))
+}
+
+async fn admin_set(pool: &Pool<Postgres>, username: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let username = username.trim();
+    if username.is_empty() {
+        return Err("Username cannot be empty".into());
+    }
+
+    let password = rpassword::prompt_password(format!("New password for '{}': ", username))?;
+    if password.len() < 8 {
+        return Err("Password must be at least 8 characters".into());
+    }
+    let confirm = rpassword::prompt_password("Repeat password: ")?;
+    if password != confirm {
+        return Err("Passwords do not match".into());
+    }
+
+    auth::set_admin(pool, username, &password).await?;
+    println!("Admin '{}' saved.", username);
+    Ok(())
+}
+
+async fn admin_delete(pool: &Pool<Postgres>, username: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let result = sqlx::query("DELETE FROM admins WHERE username = $1")
+        .bind(username)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(format!("Admin '{}' does not exist", username).into());
+    }
+    println!("Admin '{}' deleted.", username);
+    Ok(())
+}
+
+async fn admin_list(pool: &Pool<Postgres>) -> Result<(), Box<dyn std::error::Error>> {
+    let usernames: Vec<String> = sqlx::query_scalar("SELECT username FROM admins ORDER BY username")
+        .fetch_all(pool)
+        .await?;
+    for username in usernames {
+        println!("{}", username);
+    }
+    Ok(())
+}
+
+async fn thumbs_backfill(pool: &Pool<Postgres>) -> Result<(), Box<dyn std::error::Error>> {
+    // Table names come from this fixed list, never from input
+    for table in ["threads", "replies"] {
+        let rows: Vec<(i32, String)> = sqlx::query_as(&format!(
+            "SELECT id, media_url FROM {} WHERE media_type = 'image' AND media_url IS NOT NULL AND thumb_url IS NULL ORDER BY id",
+            table
+        ))
+        .fetch_all(pool)
+        .await?;
+
+        let mut created = 0;
+        for (id, media_url) in rows {
+            let path = match upload::media_path(&media_url) {
+                Some(path) => path,
+                None => {
+                    eprintln!("{} {}: unexpected media URL {}", table, id, media_url);
+                    continue;
+                }
+            };
+
+            let thumb_url = match image::open(&path).and_then(|img| upload::create_thumbnail(&img)) {
+                Ok(url) => url,
+                Err(e) => {
+                    eprintln!("{} {}: {} ({})", table, id, e, path.display());
+                    continue;
+                }
+            };
+
+            sqlx::query(&format!("UPDATE {} SET thumb_url = $1 WHERE id = $2", table))
+                .bind(&thumb_url)
+                .bind(id)
+                .execute(pool)
+                .await?;
+            created += 1;
+        }
+        println!("{}: created {} thumbnails", table, created);
+    }
+    Ok(())
+}
+
+async fn media_details(pool: &Pool<Postgres>) -> Result<(), Box<dyn std::error::Error>> {
+    // Table names come from this fixed list, never from input
+    for table in ["threads", "replies"] {
+        let rows: Vec<(i32, String, String)> = sqlx::query_as(&format!(
+            "SELECT id, media_url, media_type FROM {} WHERE media_url IS NOT NULL AND media_size IS NULL ORDER BY id",
+            table
+        ))
+        .fetch_all(pool)
+        .await?;
+
+        let mut updated = 0;
+        for (id, media_url, media_type) in rows {
+            let path = match upload::media_path(&media_url) {
+                Some(path) => path,
+                None => {
+                    eprintln!("{} {}: unexpected media URL {}", table, id, media_url);
+                    continue;
+                }
+            };
+
+            let size = match std::fs::metadata(&path) {
+                Ok(metadata) => metadata.len() as i64,
+                Err(e) => {
+                    eprintln!("{} {}: {} ({})", table, id, e, path.display());
+                    continue;
+                }
+            };
+            let dimensions = if media_type == "image" {
+                image_dimensions(&path)
+            } else {
+                None
+            };
+
+            sqlx::query(&format!(
+                "UPDATE {} SET media_size = $1, media_width = $2, media_height = $3 WHERE id = $4",
+                table
+            ))
+            .bind(size)
+            .bind(dimensions.map(|(width, _)| width as i32))
+            .bind(dimensions.map(|(_, height)| height as i32))
+            .bind(id)
+            .execute(pool)
+            .await?;
+            updated += 1;
+        }
+        println!("{}: recorded details of {} uploads", table, updated);
+    }
+    Ok(())
+}
+
+// Dimensions of a stored image as displayed. Uploads from before metadata stripping
+// may still carry an EXIF orientation that turns them on their side.
+fn image_dimensions(path: &Path) -> Option<(u32, u32)> {
+    let (width, height) = image::image_dimensions(path).ok()?;
+    let data = std::fs::read(path).ok()?;
+    let orientation = MediaKind::detect(&data)
+        .and_then(|kind| metadata::exif_orientation(kind, &data));
+    match orientation {
+        Some(5..=8) => Some((height, width)),
+        _ => Some((width, height)),
+    }
+}
+
+async fn media_gc(pool: &Pool<Postgres>, dry_run: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let report = media::collect_garbage(pool, dry_run).await?;
+    for path in &report.orphans {
+        println!("{}", path.display());
+    }
+
+    if dry_run {
+        println!(
+            "{} orphaned files ({} bytes) would be removed.",
+            report.orphans.len(),
+            report.bytes
+        );
+    } else {
+        println!(
+            "Removed {} of {} orphaned files ({} bytes).",
+            report.removed,
+            report.orphans.len(),
+            report.bytes
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,647 @@
+// src/config.rs
+//
+// Server settings, read once at startup from a TOML file (`config.toml`, or the file
+// named by `CONFIG_FILE`) and then from `CHESS_*` environment variables, which win.
+// Every setting has a default, so the file is optional. See config.example.toml.
+// Secrets are only ever read from the environment.
+
+use serde::Deserialize;
+use std::env::VarError;
+use std::fmt;
+use std::net::ToSocketAddrs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub site: SiteConfig,
+    pub database: DatabaseConfig,
+    pub paths: PathsConfig,
+    pub limits: LimitsConfig,
+    pub images: ImagesConfig,
+    pub maintenance: MaintenanceConfig,
+    pub board_defaults: BoardDefaults,
+    #[serde(skip)]
+    pub secrets: Secrets,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Address and port to listen on, e.g. `0.0.0.0:8080`.
+    pub listen: String,
+    /// Only send session cookies over HTTPS. Browsers drop secure cookies on plain
+    /// HTTP, which would break every form, so this is off unless turned on.
+    pub cookie_secure: bool,
+    /// Take the client address from `Forwarded`/`X-Forwarded-For`. Only turn this on
+    /// behind a reverse proxy that sets them, since clients can send them too.
+    pub trust_proxy_headers: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SiteConfig {
+    /// Public address of the site, e.g. `https://chess.example.org`, without a trailing
+    /// slash. Feeds build their links and entry IDs from it, so they stay the same
+    /// whatever host or proxy headers a request arrives with.
+    pub base_url: String,
+}
+
+/// The connection string itself stays in `DATABASE_URL`, next to the other secrets.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub max_connections: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PathsConfig {
+    pub static_dir: PathBuf,
+    pub image_uploads: PathBuf,
+    pub video_uploads: PathBuf,
+    pub image_thumbs: PathBuf,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Threads on each board index page, and results on each search page.
+    pub threads_per_page: i32,
+    /// Longest thread title, in characters.
+    pub title_max_len: usize,
+    /// Longest poster name, in characters, not counting a tripcode secret.
+    pub name_max_len: usize,
+    /// Longest thread or reply message, in characters.
+    pub message_max_len: usize,
+    /// Largest image upload, in bytes. Bigger uploads are cut off mid-stream.
+    pub max_image_bytes: u64,
+    /// Largest video upload, in bytes.
+    pub max_video_bytes: u64,
+    /// Most pixels (width × height) an uploaded image may have. Checked from the
+    /// image header before decoding, so small files cannot expand into huge images.
+    pub max_image_pixels: u64,
+    /// Seconds during which a poster cannot post the same message again on a board;
+    /// 0 allows it.
+    pub duplicate_window: i64,
+    /// Longest reason given with a report, in characters.
+    pub report_reason_max_len: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImagesConfig {
+    /// Remove EXIF, XMP and comments from uploaded images. JPEG and PNG files are
+    /// re-encoded; GIF and WEBP files have the metadata cut out. When off, uploads
+    /// are stored as sent.
+    pub strip_metadata: bool,
+    /// Quality (1-100) used when re-encoding JPEGs.
+    pub jpeg_quality: u8,
+    /// Uploads whose perceptual hash differs from a banned image's in at most this
+    /// many of its 64 bits are refused as copies of it.
+    pub phash_max_distance: u32,
+    /// Thumbnails fit inside a square of this many pixels. Changing it only affects
+    /// thumbnails made afterwards.
+    pub thumb_max_dimension: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MaintenanceConfig {
+    /// Seconds between scans of the upload directories for files no post uses.
+    pub gc_interval: u64,
+}
+
+/// Read from `SESSION_KEY`, `POSTER_HASH_SALT` and `TRIPCODE_SECRET`, never from the
+/// config file. An empty salt or tripcode secret is replaced by a random one at startup.
+#[derive(Default)]
+pub struct Secrets {
+    /// Signs session cookies; at least 64 bytes.
+    pub session_key: String,
+    pub poster_hash_salt: String,
+    pub tripcode_secret: String,
+}
+
+/// Settings filled in on the admin form for new boards, and the only source of them:
+/// the board columns have no defaults in the database. Existing boards keep their own
+/// settings, which are edited per board in the admin panel; boards created before a
+/// setting existed were given the value its migration added the column with.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BoardDefaults {
+    pub bump_limit: i32,
+    pub max_threads: i32,
+    pub archive_pruned: bool,
+    pub thread_cooldown: i32,
+    pub reply_cooldown: i32,
+    pub repost_days: i32,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            listen: "0.0.0.0:8080".to_string(),
+            cookie_secure: false,
+            trust_proxy_headers: false,
+        }
+    }
+}
+
+impl Default for SiteConfig {
+    fn default() -> Self {
+        SiteConfig {
+            base_url: "http://localhost:8080".to_string(),
+        }
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig { max_connections: 10 }
+    }
+}
+
+impl Default for PathsConfig {
+    fn default() -> Self {
+        PathsConfig {
+            static_dir: PathBuf::from("./static"),
+            image_uploads: PathBuf::from("./uploads/images"),
+            video_uploads: PathBuf::from("./uploads/videos"),
+            image_thumbs: PathBuf::from("./thumbs/images"),
+        }
+    }
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        LimitsConfig {
+            threads_per_page: 10,
+            title_max_len: 75,
+            name_max_len: 50,
+            message_max_len: 8000,
+            max_image_bytes: 8 * 1024 * 1024,
+            max_video_bytes: 32 * 1024 * 1024,
+            max_image_pixels: 50_000_000,
+            duplicate_window: 10 * 60,
+            report_reason_max_len: 200,
+        }
+    }
+}
+
+impl Default for ImagesConfig {
+    fn default() -> Self {
+        ImagesConfig {
+            strip_metadata: true,
+            jpeg_quality: 90,
+            phash_max_distance: 8,
+            thumb_max_dimension: 250,
+        }
+    }
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        MaintenanceConfig {
+            gc_interval: 6 * 60 * 60,
+        }
+    }
+}
+
+impl Default for BoardDefaults {
+    fn default() -> Self {
+        BoardDefaults {
+            bump_limit: 300,
+            max_threads: 150,
+            archive_pruned: false,
+            thread_cooldown: 60,
+            reply_cooldown: 10,
+            repost_days: 0,
+        }
+    }
+}
+
+/// Why the configuration could not be loaded; the message names the offending setting.
+#[derive(Debug)]
+pub struct ConfigError(String);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+// Replaces `field` with the parsed value of an environment variable, if it is set
+fn env_override<T>(name: &str, field: &mut T) -> Result<(), ConfigError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    match std::env::var(name) {
+        Ok(value) => {
+            *field = value.trim().parse().map_err(|e| {
+                ConfigError(format!("{}={:?} is not a valid value: {}", name, value, e))
+            })?;
+            Ok(())
+        }
+        Err(VarError::NotPresent) => Ok(()),
+        Err(VarError::NotUnicode(_)) => Err(ConfigError(format!("{} is not valid UTF-8", name))),
+    }
+}
+
+// Reads a secret exactly as set, without the trimming done to settings
+fn env_secret(name: &str, field: &mut String) -> Result<(), ConfigError> {
+    match std::env::var(name) {
+        Ok(value) => {
+            *field = value;
+            Ok(())
+        }
+        Err(VarError::NotPresent) => Ok(()),
+        Err(VarError::NotUnicode(_)) => Err(ConfigError(format!("{} is not valid UTF-8", name))),
+    }
+}
+
+// Honours the name a flag had in the environment before it became a setting
+fn legacy_flag(old: &str, new: &str, field: &mut bool) {
+    if let Ok(value) = std::env::var(old) {
+        log::warn!("{} is deprecated; set {} or the config file setting instead", old, new);
+        *field = value == "true" || value == "1";
+    }
+}
+
+fn check(condition: bool, message: impl FnOnce() -> String) -> Result<(), ConfigError> {
+    if condition {
+        Ok(())
+    } else {
+        Err(ConfigError(message()))
+    }
+}
+
+impl Config {
+    /// Reads the config file, applies environment overrides and validates the result.
+    /// A missing `config.toml` is fine; a missing file named by `CONFIG_FILE` is not.
+    pub fn load() -> Result<Config, ConfigError> {
+        let (path, required) = match std::env::var("CONFIG_FILE") {
+            Ok(path) => (PathBuf::from(path), true),
+            Err(_) => (PathBuf::from(DEFAULT_CONFIG_FILE), false),
+        };
+
+        let mut config = if required || path.exists() {
+            Config::from_file(&path)?
+        } else {
+            Config::default()
+        };
+        config.apply_env()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn from_file(path: &Path) -> Result<Config, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            ConfigError(format!("Cannot read config file {}: {}", path.display(), e))
+        })?;
+        toml::from_str(&text)
+            .map_err(|e| ConfigError(format!("Invalid config file {}: {}", path.display(), e)))
+    }
+
+    fn apply_env(&mut self) -> Result<(), ConfigError> {
+        legacy_flag(
+            "SESSION_COOKIE_SECURE",
+            "CHESS_SERVER_COOKIE_SECURE",
+            &mut self.server.cookie_secure,
+        );
+        legacy_flag(
+            "TRUST_PROXY_HEADERS",
+            "CHESS_SERVER_TRUST_PROXY_HEADERS",
+            &mut self.server.trust_proxy_headers,
+        );
+        env_override("CHESS_SERVER_LISTEN", &mut self.server.listen)?;
+        env_override("CHESS_SERVER_COOKIE_SECURE", &mut self.server.cookie_secure)?;
+        env_override(
+            "CHESS_SERVER_TRUST_PROXY_HEADERS",
+            &mut self.server.trust_proxy_headers,
+        )?;
+        env_override("CHESS_SITE_BASE_URL", &mut self.site.base_url)?;
+        env_override("CHESS_DATABASE_MAX_CONNECTIONS", &mut self.database.max_connections)?;
+        env_override("CHESS_PATHS_STATIC_DIR", &mut self.paths.static_dir)?;
+        env_override("CHESS_PATHS_IMAGE_UPLOADS", &mut self.paths.image_uploads)?;
+        env_override("CHESS_PATHS_VIDEO_UPLOADS", &mut self.paths.video_uploads)?;
+        env_override("CHESS_PATHS_IMAGE_THUMBS", &mut self.paths.image_thumbs)?;
+        env_override("CHESS_LIMITS_THREADS_PER_PAGE", &mut self.limits.threads_per_page)?;
+        env_override("CHESS_LIMITS_TITLE_MAX_LEN", &mut self.limits.title_max_len)?;
+        env_override("CHESS_LIMITS_NAME_MAX_LEN", &mut self.limits.name_max_len)?;
+        env_override("CHESS_LIMITS_MESSAGE_MAX_LEN", &mut self.limits.message_max_len)?;
+        env_override("CHESS_LIMITS_MAX_IMAGE_BYTES", &mut self.limits.max_image_bytes)?;
+        env_override("CHESS_LIMITS_MAX_VIDEO_BYTES", &mut self.limits.max_video_bytes)?;
+        env_override("CHESS_LIMITS_MAX_IMAGE_PIXELS", &mut self.limits.max_image_pixels)?;
+        env_override("CHESS_LIMITS_DUPLICATE_WINDOW", &mut self.limits.duplicate_window)?;
+        env_override(
+            "CHESS_LIMITS_REPORT_REASON_MAX_LEN",
+            &mut self.limits.report_reason_max_len,
+        )?;
+        env_override("CHESS_IMAGES_STRIP_METADATA", &mut self.images.strip_metadata)?;
+        env_override("CHESS_IMAGES_JPEG_QUALITY", &mut self.images.jpeg_quality)?;
+        env_override(
+            "CHESS_IMAGES_PHASH_MAX_DISTANCE",
+            &mut self.images.phash_max_distance,
+        )?;
+        env_override(
+            "CHESS_IMAGES_THUMB_MAX_DIMENSION",
+            &mut self.images.thumb_max_dimension,
+        )?;
+        env_override("CHESS_MAINTENANCE_GC_INTERVAL", &mut self.maintenance.gc_interval)?;
+        env_override("CHESS_BOARD_DEFAULTS_BUMP_LIMIT", &mut self.board_defaults.bump_limit)?;
+        env_override("CHESS_BOARD_DEFAULTS_MAX_THREADS", &mut self.board_defaults.max_threads)?;
+        env_override(
+            "CHESS_BOARD_DEFAULTS_ARCHIVE_PRUNED",
+            &mut self.board_defaults.archive_pruned,
+        )?;
+        env_override(
+            "CHESS_BOARD_DEFAULTS_THREAD_COOLDOWN",
+            &mut self.board_defaults.thread_cooldown,
+        )?;
+        env_override(
+            "CHESS_BOARD_DEFAULTS_REPLY_COOLDOWN",
+            &mut self.board_defaults.reply_cooldown,
+        )?;
+        env_override(
+            "CHESS_BOARD_DEFAULTS_REPOST_DAYS",
+            &mut self.board_defaults.repost_days,
+        )?;
+        env_secret("SESSION_KEY", &mut self.secrets.session_key)?;
+        env_secret("POSTER_HASH_SALT", &mut self.secrets.poster_hash_salt)?;
+        env_secret("TRIPCODE_SECRET", &mut self.secrets.tripcode_secret)?;
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let listen = &self.server.listen;
+        let resolves = listen
+            .to_socket_addrs()
+            .map(|mut addrs| addrs.next().is_some())
+            .unwrap_or(false);
+        check(resolves, || {
+            format!("server.listen = {:?} is not a valid address such as 0.0.0.0:8080", listen)
+        })?;
+
+        let base_url = &self.site.base_url;
+        check(
+            (base_url.starts_with("http://") || base_url.starts_with("https://"))
+                && !base_url.ends_with('/'),
+            || {
+                format!(
+                    "site.base_url = {:?} must start with http:// or https:// and not end with /",
+                    base_url
+                )
+            },
+        )?;
+
+        check(self.database.max_connections >= 1, || {
+            "database.max_connections must be at least 1".to_string()
+        })?;
+
+        for (name, path) in [
+            ("paths.static_dir", &self.paths.static_dir),
+            ("paths.image_uploads", &self.paths.image_uploads),
+            ("paths.video_uploads", &self.paths.video_uploads),
+            ("paths.image_thumbs", &self.paths.image_thumbs),
+        ] {
+            check(!path.as_os_str().is_empty(), || format!("{} cannot be empty", name))?;
+        }
+
+        let limits = &self.limits;
+        check((1..=100).contains(&limits.threads_per_page), || {
+            format!(
+                "limits.threads_per_page must be between 1 and 100 (got {})",
+                limits.threads_per_page
+            )
+        })?;
+        check(limits.title_max_len >= 1, || {
+            "limits.title_max_len must be at least 1".to_string()
+        })?;
+        check(limits.name_max_len >= 1, || {
+            "limits.name_max_len must be at least 1".to_string()
+        })?;
+        check(limits.message_max_len >= 1, || {
+            "limits.message_max_len must be at least 1".to_string()
+        })?;
+        check(limits.report_reason_max_len >= 1, || {
+            "limits.report_reason_max_len must be at least 1".to_string()
+        })?;
+        for (name, value) in [
+            ("limits.max_image_bytes", limits.max_image_bytes),
+            ("limits.max_video_bytes", limits.max_video_bytes),
+            ("limits.max_image_pixels", limits.max_image_pixels),
+        ] {
+            check(value >= 1, || format!("{} must be at least 1", name))?;
+        }
+        check(limits.duplicate_window >= 0, || {
+            "limits.duplicate_window cannot be negative".to_string()
+        })?;
+
+        check((1..=100).contains(&self.images.jpeg_quality), || {
+            format!(
+                "images.jpeg_quality must be between 1 and 100 (got {})",
+                self.images.jpeg_quality
+            )
+        })?;
+        // Half the bits differ between unrelated images, so anything near 32 bans everything
+        check(self.images.phash_max_distance <= 24, || {
+            format!(
+                "images.phash_max_distance must be at most 24 (got {})",
+                self.images.phash_max_distance
+            )
+        })?;
+        check(self.images.thumb_max_dimension >= 1, || {
+            "images.thumb_max_dimension must be at least 1".to_string()
+        })?;
+
+        check(self.maintenance.gc_interval >= 60, || {
+            format!(
+                "maintenance.gc_interval must be at least 60 seconds (got {})",
+                self.maintenance.gc_interval
+            )
+        })?;
+
+        // Same rules as the admin board form
+        let boards = &self.board_defaults;
+        check(boards.bump_limit >= 1 && boards.max_threads >= 1, || {
+            "board_defaults.bump_limit and board_defaults.max_threads must be at least 1"
+                .to_string()
+        })?;
+        check(boards.thread_cooldown >= 0 && boards.reply_cooldown >= 0, || {
+            "board_defaults.thread_cooldown and board_defaults.reply_cooldown cannot be negative"
+                .to_string()
+        })?;
+        check(boards.repost_days >= 0, || {
+            "board_defaults.repost_days cannot be negative".to_string()
+        })?;
+
+        // A random key would log everyone out and expire every form on each restart
+        let session_key = &self.secrets.session_key;
+        check(session_key.len() >= 64, || {
+            format!(
+                "SESSION_KEY must be set to at least 64 random bytes (got {}); generate one \
+                 with `openssl rand -hex 32`",
+                session_key.len()
+            )
+        })?;
+        Ok(())
+    }
+}
+
+/// Makes `config` available through `get`. Called once from `main`.
+pub fn init(config: Config) {
+    if CONFIG.set(config).is_err() {
+        panic!("configuration initialised twice");
+    }
+}
+
+/// The configuration loaded at startup.
+pub fn get() -> &'static Config {
+    CONFIG.get().expect("configuration not initialised")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Parses and validates a config file body, leaving out environment overrides so
+    // tests do not depend on the environment they run in
+    fn parse(text: &str) -> Result<Config, ConfigError> {
+        let mut config: Config =
+            toml::from_str(text).map_err(|e| ConfigError(e.to_string()))?;
+        config.secrets.session_key = "k".repeat(64);
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn assert_refused(text: &str, setting: &str) {
+        match parse(text) {
+            Ok(_) => panic!("{:?} was accepted", text),
+            Err(e) => assert!(e.to_string().contains(setting), "{:?} gave {:?}", text, e.0),
+        }
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        assert!(parse("").is_ok());
+    }
+
+    #[test]
+    fn missing_or_short_session_key_is_refused() {
+        let mut config = Config::default();
+        for key in [String::new(), "k".repeat(63)] {
+            config.secrets.session_key = key;
+            match config.validate() {
+                Ok(_) => panic!("a {}-byte key was accepted", config.secrets.session_key.len()),
+                Err(e) => assert!(e.0.contains("SESSION_KEY"), "{:?}", e.0),
+            }
+        }
+    }
+
+    #[test]
+    fn secrets_are_not_read_from_the_file() {
+        assert_refused("[secrets]\nsession_key = \"k\"", "secrets");
+    }
+
+    #[test]
+    fn example_file_is_valid() {
+        let config = parse(include_str!("../config.example.toml")).unwrap();
+        assert_eq!(config.server.listen, ServerConfig::default().listen);
+        assert_eq!(config.limits.threads_per_page, LimitsConfig::default().threads_per_page);
+    }
+
+    #[test]
+    fn settings_are_read_from_their_sections() {
+        let config = parse(
+            r#"
+            [server]
+            listen = "127.0.0.1:9000"
+            trust_proxy_headers = true
+            [limits]
+            threads_per_page = 15
+            [board_defaults]
+            archive_pruned = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.server.listen, "127.0.0.1:9000");
+        assert!(config.server.trust_proxy_headers);
+        assert_eq!(config.limits.threads_per_page, 15);
+        assert!(config.board_defaults.archive_pruned);
+        // Settings left out keep their defaults
+        assert_eq!(config.limits.title_max_len, LimitsConfig::default().title_max_len);
+    }
+
+    #[test]
+    fn unknown_settings_are_refused() {
+        assert_refused("[limits]\nthreads_per_pgae = 15", "threads_per_pgae");
+        assert_refused("[limts]\nthreads_per_page = 15", "limts");
+    }
+
+    #[test]
+    fn out_of_range_values_are_refused() {
+        for (text, setting) in [
+            ("[server]\nlisten = \"8080\"", "server.listen"),
+            ("[site]\nbase_url = \"chess.example.org\"", "site.base_url"),
+            ("[site]\nbase_url = \"https://chess.example.org/\"", "site.base_url"),
+            ("[database]\nmax_connections = 0", "database.max_connections"),
+            ("[paths]\nimage_uploads = \"\"", "paths.image_uploads"),
+            ("[limits]\nthreads_per_page = 0", "limits.threads_per_page"),
+            ("[limits]\nthreads_per_page = 101", "limits.threads_per_page"),
+            ("[limits]\ntitle_max_len = 0", "limits.title_max_len"),
+            ("[limits]\nname_max_len = 0", "limits.name_max_len"),
+            ("[limits]\nmessage_max_len = 0", "limits.message_max_len"),
+            ("[limits]\nmax_video_bytes = 0", "limits.max_video_bytes"),
+            ("[limits]\nduplicate_window = -1", "limits.duplicate_window"),
+            ("[limits]\nreport_reason_max_len = 0", "limits.report_reason_max_len"),
+            ("[images]\njpeg_quality = 0", "images.jpeg_quality"),
+            ("[images]\njpeg_quality = 101", "images.jpeg_quality"),
+            ("[images]\nphash_max_distance = 25", "images.phash_max_distance"),
+            ("[images]\nthumb_max_dimension = 0", "images.thumb_max_dimension"),
+            ("[maintenance]\ngc_interval = 59", "maintenance.gc_interval"),
+            ("[board_defaults]\nmax_threads = 0", "board_defaults.max_threads"),
+            ("[board_defaults]\nreply_cooldown = -1", "board_defaults.reply_cooldown"),
+            ("[board_defaults]\nrepost_days = -1", "board_defaults.repost_days"),
+        ] {
+            assert_refused(text, setting);
+        }
+    }
+
+    #[test]
+    fn boundary_values_are_accepted() {
+        assert!(parse(
+            r#"
+            [limits]
+            threads_per_page = 100
+            duplicate_window = 0
+            [images]
+            jpeg_quality = 100
+            phash_max_distance = 24
+            thumb_max_dimension = 1
+            [maintenance]
+            gc_interval = 60
+            [board_defaults]
+            thread_cooldown = 0
+            reply_cooldown = 0
+            repost_days = 0
+            "#,
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn values_of_the_wrong_type_are_refused() {
+        assert_refused("[limits]\nthreads_per_page = \"ten\"", "threads_per_page");
+        assert_refused("[board_defaults]\narchive_pruned = \"yes\"", "archive_pruned");
+    }
+}
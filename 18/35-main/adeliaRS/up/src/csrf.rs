@@ -0,0 +1,240 @@
+// src/csrf.rs
+//
+// Per-session CSRF tokens. Every form carries the token of the session that rendered it,
+// and `verify` rejects state-changing requests whose token is missing or does not match.
+
+use actix_session::{Session, SessionExt};
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    http::{header, Method},
+    middleware::Next,
+    web, Error, FromRequest, HttpMessage, HttpRequest, HttpResponse,
+};
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use futures_util::future::{ready, Ready};
+use futures_util::stream::{self, StreamExt};
+use mime_guess::mime;
+use serde::Deserialize;
+
+const SESSION_CSRF_KEY: &str = "csrf";
+
+/// Name of the form field that carries the token.
+pub const FIELD: &str = "csrf_token";
+
+/// Header accepted in place of the form field, for scripted clients.
+const HEADER: &str = "x-csrf-token";
+
+/// Most of a multipart body read while looking for the token in its first field.
+const MULTIPART_PEEK_LEN: usize = 16 * 1024;
+
+#[derive(Deserialize)]
+struct TokenField {
+    csrf_token: Option<String>,
+}
+
+/// Returns the session's token, creating one on first use.
+fn session_token(session: &Session) -> Result<String, Error> {
+    if let Some(token) = session.get::<String>(SESSION_CSRF_KEY).ok().flatten() {
+        return Ok(token);
+    }
+
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    session
+        .insert(SESSION_CSRF_KEY, &token)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(token)
+}
+
+/// Extractor for handlers that render forms.
+pub struct CsrfToken(String);
+
+impl CsrfToken {
+    /// Hidden input to place inside a `<form>`. In a multipart form it must be the
+    /// first field, as the middleware only reads that far into the body.
+    pub fn field(&self) -> String {
+        format!(r#"<input type="hidden" name="{}" value="{}">"#, FIELD, self.0)
+    }
+}
+
+impl FromRequest for CsrfToken {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(session_token(&req.get_session()).map(CsrfToken))
+    }
+}
+
+/// Compares two tokens in time independent of where they first differ.
+fn tokens_match(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+fn token_from_form(body: &str) -> Option<String> {
+    web::Query::<TokenField>::from_query(body)
+        .ok()
+        .and_then(|q| q.into_inner().csrf_token)
+}
+
+/// Finds the submitted token in the header or the form body. A url-encoded body is
+/// read in full and a multipart one up to the end of its first field, and what was
+/// read is handed back to the request for the handler.
+async fn submitted_token(req: &mut ServiceRequest) -> Result<Option<String>, Error> {
+    if let Some(token) = req
+        .headers()
+        .get(HEADER)
+        .and_then(|value| value.to_str().ok())
+    {
+        return Ok(Some(token.to_string()));
+    }
+
+    let content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<mime::Mime>().ok());
+    let Some(content_type) = content_type else {
+        return Ok(None);
+    };
+
+    if content_type.essence_str() == "application/x-www-form-urlencoded" {
+        let body = req.extract::<web::Bytes>().await?;
+        let token = std::str::from_utf8(&body).ok().and_then(token_from_form);
+        req.set_payload(Payload::from(body));
+        return Ok(token);
+    }
+    match content_type.get_param(mime::BOUNDARY) {
+        Some(boundary) if content_type.essence_str() == "multipart/form-data" => {
+            token_from_multipart(req, boundary.as_str()).await
+        }
+        _ => Ok(None),
+    }
+}
+
+// Reads a multipart body until its first part is complete, so an upload that follows
+// the token is left streaming to the handler, then puts the bytes read back in front
+// of the rest of the body
+async fn token_from_multipart(
+    req: &mut ServiceRequest,
+    boundary: &str,
+) -> Result<Option<String>, Error> {
+    let mut payload = req.take_payload();
+    let mut head = web::BytesMut::new();
+    let token = loop {
+        if let Some(token) = first_part_token(&head, boundary) {
+            break token;
+        }
+        if head.len() >= MULTIPART_PEEK_LEN {
+            break None;
+        }
+        match payload.next().await {
+            Some(chunk) => head.extend_from_slice(&chunk?),
+            None => break None,
+        }
+    };
+
+    let rest = stream::once(ready(Ok(head.freeze()))).chain(payload);
+    req.set_payload(Payload::Stream {
+        payload: Box::pin(rest),
+    });
+    Ok(token)
+}
+
+// Looks at the first part of a multipart body. Returns `None` while more of the body
+// is needed to tell, and otherwise the part's value if it is the token field.
+fn first_part_token(head: &[u8], boundary: &str) -> Option<Option<String>> {
+    let opening = format!("--{}\r\n", boundary);
+    if head.len() < opening.len() {
+        return None;
+    }
+    let Some(part) = head.strip_prefix(opening.as_bytes()) else {
+        return Some(None);
+    };
+    let headers_end = find(part, b"\r\n\r\n")?;
+    let headers = String::from_utf8_lossy(&part[..headers_end]);
+    if !headers.contains(&format!("; name=\"{}\"", FIELD)) {
+        return Some(None);
+    }
+    let value = &part[headers_end + 4..];
+    let value_end = find(value, format!("\r\n--{}", boundary).as_bytes())?;
+    Some(std::str::from_utf8(&value[..value_end]).ok().map(str::to_string))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// Middleware that rejects state-changing requests without the session's token.
+pub async fn verify<B: MessageBody>(
+    mut req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    }
+
+    let expected = req
+        .get_session()
+        .get::<String>(SESSION_CSRF_KEY)
+        .ok()
+        .flatten();
+    let submitted = submitted_token(&mut req).await?;
+
+    match (expected, submitted) {
+        (Some(expected), Some(submitted)) if tokens_match(&expected, &submitted) => {
+            next.call(req).await.map(ServiceResponse::map_into_left_body)
+        }
+        _ => {
+            let response = HttpResponse::Forbidden()
+                .content_type("text/html")
+                .body(crate::render_error_page(
+                    "Form Expired",
+                    "The form was missing its security token or has expired. Go back, reload the page and try again.",
+                ));
+            Ok(req.into_response(response).map_into_right_body())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOUNDARY: &str = "----form";
+
+    fn part(name: &str, value: &str) -> String {
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+            BOUNDARY, name, value
+        )
+    }
+
+    #[test]
+    fn token_is_read_from_the_first_part() {
+        let body = part(FIELD, "abc123") + &part("message", "hello");
+        assert_eq!(first_part_token(body.as_bytes(), BOUNDARY), Some(Some("abc123".into())));
+    }
+
+    #[test]
+    fn more_of_the_body_is_needed_until_the_first_part_ends() {
+        let body = part(FIELD, "abc123");
+        for cut in [0, 4, 30, body.len() - 3] {
+            assert_eq!(first_part_token(&body.as_bytes()[..cut], BOUNDARY), None, "{}", cut);
+        }
+    }
+
+    #[test]
+    fn token_after_other_fields_is_not_looked_for() {
+        let body = part("message", "hello") + &part(FIELD, "abc123");
+        assert_eq!(first_part_token(body.as_bytes(), BOUNDARY), Some(None));
+        let body = part("not_csrf_token", "abc123");
+        assert_eq!(first_part_token(body.as_bytes(), BOUNDARY), Some(None));
+    }
+}
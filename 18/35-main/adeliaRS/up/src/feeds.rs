@@ -0,0 +1,310 @@
+// src/feeds.rs
+//
+// Atom feeds of new threads on a board and new replies in a thread.
+// Entry content is the same HTML the pages show, escaped once more for XML.
+
+use crate::board::{Board, BoardRegistry};
+use crate::markup::{self, QuoteTargets};
+use crate::{config, tripcode};
+use crate::{escape_html, Reply, Thread, REPLY_COLUMNS, THREAD_COLUMNS};
+use html_escape::encode_quoted_attribute;
+use actix_web::{web, Error, HttpResponse};
+use chrono::{DateTime, SecondsFormat};
+use sqlx::{Pool, Postgres};
+
+/// Entries in each feed, newest first.
+const FEED_ENTRIES: i64 = 50;
+
+/// Characters of a reply's message used as its entry title.
+const ENTRY_TITLE_LEN: usize = 60;
+
+const ATOM_CONTENT_TYPE: &str = "application/atom+xml; charset=utf-8";
+
+#[derive(sqlx::FromRow)]
+struct FeedThread {
+    #[sqlx(flatten)]
+    thread: Thread,
+    created_at: i64,
+}
+
+#[derive(sqlx::FromRow)]
+struct FeedReply {
+    #[sqlx(flatten)]
+    reply: Reply,
+    created_at: i64,
+}
+
+// Feeds need absolute links, and entry IDs must never change, so both come from the
+// configured site address rather than the request. Relative links inside entry
+// content resolve against the feed's xml:base.
+fn base_url() -> &'static str {
+    &config::get().site.base_url
+}
+
+// Escapes text for XML. Control characters are dropped, since XML 1.0 cannot
+// represent them even as character references.
+fn escape_xml(input: &str) -> String {
+    let text: String = input
+        .chars()
+        .filter(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'))
+        .collect();
+    encode_quoted_attribute(&text).to_string()
+}
+
+fn rfc3339(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+// Short single-line title for an entry that has none of its own
+fn entry_title(post_no: i32, message: &str) -> String {
+    let line = message.lines().next().unwrap_or("").trim();
+    let mut title: String = line.chars().take(ENTRY_TITLE_LEN).collect();
+    if line.chars().count() > ENTRY_TITLE_LEN {
+        title.push('…');
+    }
+    if title.is_empty() {
+        format!("No. {}", post_no)
+    } else {
+        format!("No. {}: {}", post_no, title)
+    }
+}
+
+fn author(name: Option<&str>, tripcode: Option<&str>) -> String {
+    let name = name.unwrap_or(tripcode::DEFAULT_NAME);
+    match tripcode {
+        Some(tripcode) => format!("{} {}", name, tripcode),
+        None => name.to_string(),
+    }
+}
+
+struct Entry {
+    id: String,
+    title: String,
+    link: String,
+    /// Poster name and tripcode.
+    author: String,
+    published: i64,
+    updated: i64,
+    /// Rendered HTML; escaped when written into the feed.
+    content: String,
+}
+
+fn render_feed(
+    id: &str,
+    title: &str,
+    link: &str,
+    self_link: &str,
+    updated: i64,
+    entries: &[Entry],
+) -> String {
+    let entries_xml = entries
+        .iter()
+        .map(|entry| {
+            format!(
+                r#"  <entry>
+    <id>{}</id>
+    <title>{}</title>
+    <link rel="alternate" type="text/html" href="{}"/>
+    <published>{}</published>
+    <updated>{}</updated>
+    <author><name>{}</name></author>
+    <content type="html">{}</content>
+  </entry>
+"#,
+                escape_xml(&entry.id),
+                escape_xml(&entry.title),
+                escape_xml(&entry.link),
+                rfc3339(entry.published),
+                rfc3339(entry.updated),
+                escape_xml(&entry.author),
+                escape_xml(&entry.content)
+            )
+        })
+        .collect::<String>();
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xml:base="{}">
+  <id>{}</id>
+  <title>{}</title>
+  <link rel="alternate" type="text/html" href="{}"/>
+  <link rel="self" type="application/atom+xml" href="{}"/>
+  <updated>{}</updated>
+{}</feed>
+"#,
+        escape_xml(link),
+        escape_xml(id),
+        escape_xml(title),
+        escape_xml(link),
+        escape_xml(self_link),
+        rfc3339(updated),
+        entries_xml
+    )
+}
+
+fn feed_not_found() -> HttpResponse {
+    HttpResponse::NotFound()
+        .content_type("text/plain")
+        .body("Feed not found")
+}
+
+/// `<link>` tag advertising a feed to browsers and feed readers.
+pub fn alternate_link(href: &str, title: &str) -> String {
+    format!(
+        r#"<link rel="alternate" type="application/atom+xml" href="{}" title="{}">"#,
+        escape_html(href),
+        escape_html(title)
+    )
+}
+
+pub fn board_feed_url(board: &Board) -> String {
+    format!("/board/{}/feed.atom", board.id)
+}
+
+pub fn thread_feed_url(thread_id: i32) -> String {
+    format!("/thread/{}/feed.atom", thread_id)
+}
+
+// GET /board/{id}/feed.atom: the newest threads on a board
+pub async fn board_feed(
+    pool: web::Data<Pool<Postgres>>,
+    registry: web::Data<BoardRegistry>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    let board = match registry.get(path.into_inner()) {
+        Some(board) => board,
+        None => return Ok(feed_not_found()),
+    };
+
+    let threads = sqlx::query_as::<_, FeedThread>(&format!(
+        r#"SELECT {}, created_at FROM threads
+        WHERE board_id = $1 AND NOT archived
+        ORDER BY created_at DESC, id DESC
+        LIMIT $2"#,
+        THREAD_COLUMNS
+    ))
+    .bind(board.id)
+    .bind(FEED_ENTRIES)
+    .fetch_all(pool.get_ref())
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let targets = QuoteTargets::resolve(
+        pool.get_ref(),
+        &registry,
+        &board.uri,
+        threads.iter().map(|t| t.thread.message.as_str()),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let base = base_url();
+    let entries: Vec<Entry> = threads
+        .iter()
+        .map(|FeedThread { thread, created_at }| {
+            let link = format!("{}/thread/{}", base, thread.id);
+            Entry {
+                id: format!("{}#p{}", link, thread.post_no),
+                title: thread.title.clone(),
+                link,
+                author: author(thread.name.as_deref(), thread.tripcode.as_deref()),
+                published: *created_at,
+                updated: thread.last_updated,
+                content: markup::render_message(&thread.message, &board.uri, &targets),
+            }
+        })
+        .collect();
+
+    let board_link = format!("{}/{}/", base, board.uri);
+    let updated = entries.iter().map(|e| e.updated).max().unwrap_or_default();
+    let xml = render_feed(
+        &board_link,
+        &format!("/{}/ - {}", board.uri, board.name),
+        &board_link,
+        &format!("{}{}", base, board_feed_url(&board)),
+        updated,
+        &entries,
+    );
+
+    Ok(HttpResponse::Ok().content_type(ATOM_CONTENT_TYPE).body(xml))
+}
+
+// GET /thread/{id}/feed.atom: the newest replies in a thread
+pub async fn thread_feed(
+    pool: web::Data<Pool<Postgres>>,
+    registry: web::Data<BoardRegistry>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    let thread: Option<Thread> = sqlx::query_as(&format!(
+        "SELECT {} FROM threads WHERE id = $1",
+        THREAD_COLUMNS
+    ))
+    .bind(path.into_inner())
+    .fetch_optional(pool.get_ref())
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    // Threads on deleted boards are hidden along with the board
+    let (thread, board) = match thread.and_then(|t| registry.get(t.board_id).map(|b| (t, b))) {
+        Some(found) => found,
+        None => return Ok(feed_not_found()),
+    };
+
+    let replies = sqlx::query_as::<_, FeedReply>(&format!(
+        r#"SELECT {}, created_at FROM replies
+        WHERE thread_id = $1
+        ORDER BY created_at DESC, id DESC
+        LIMIT $2"#,
+        REPLY_COLUMNS
+    ))
+    .bind(thread.id)
+    .bind(FEED_ENTRIES)
+    .fetch_all(pool.get_ref())
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let targets = QuoteTargets::resolve(
+        pool.get_ref(),
+        &registry,
+        &board.uri,
+        replies.iter().map(|r| r.reply.message.as_str()),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let base = base_url();
+    let thread_link = format!("{}/thread/{}", base, thread.id);
+    let entries: Vec<Entry> = replies
+        .iter()
+        .map(|FeedReply { reply, created_at }| {
+            let link = format!("{}#p{}", thread_link, reply.post_no);
+            Entry {
+                id: link.clone(),
+                title: entry_title(reply.post_no, &reply.message),
+                link,
+                author: author(reply.name.as_deref(), reply.tripcode.as_deref()),
+                published: *created_at,
+                updated: *created_at,
+                content: markup::render_message(&reply.message, &board.uri, &targets),
+            }
+        })
+        .collect();
+
+    // Saged replies do not bump the thread, so the newest reply can be later than last_updated
+    let updated = entries
+        .iter()
+        .map(|e| e.updated)
+        .fold(thread.last_updated, i64::max);
+    let xml = render_feed(
+        &thread_link,
+        &format!("/{}/ - {}", board.uri, thread.title),
+        &thread_link,
+        &format!("{}{}", base, thread_feed_url(thread.id)),
+        updated,
+        &entries,
+    );
+
+    Ok(HttpResponse::Ok().content_type(ATOM_CONTENT_TYPE).body(xml))
+}
@@ -0,0 +1,253 @@
+// src/hashes.rs
+//
+// SHA-256 hashes of uploaded files. They let identical uploads share one stored
+// copy, enforce each board's repost rule and check uploads against the admin
+// blocklist of banned files.
+//
+// Images also get a perceptual hash, which survives resizing and recompression,
+// for the blocklist of banned images.
+
+use crate::board::Board;
+use crate::sniff::MediaKind;
+use crate::upload::{self, SavedMedia};
+use crate::{config, metadata, render_error_page};
+use actix_web::HttpResponse;
+use image::DynamicImage;
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Postgres};
+use std::path::Path;
+
+#[derive(sqlx::FromRow)]
+pub struct BannedFile {
+    pub id: i32,
+    pub sha256: String,
+    pub reason: String,
+    pub created_by: String,
+    pub created_at: i64,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct BannedImage {
+    pub id: i32,
+    pub phash: i64,
+    pub reason: String,
+    pub created_by: String,
+    pub created_at: i64,
+}
+
+/// Hex SHA-256 of a file's contents.
+pub fn file_sha256(path: &Path) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Checks a hash typed by an admin. Returns it in lowercase, or `None` if it is not
+/// 64 hex digits.
+pub fn parse_sha256(input: &str) -> Option<String> {
+    let input = input.trim();
+    if input.len() == 64 && input.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(input.to_ascii_lowercase())
+    } else {
+        None
+    }
+}
+
+pub async fn is_banned(pool: &Pool<Postgres>, sha256: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM banned_files WHERE sha256 = $1)")
+        .bind(sha256)
+        .fetch_one(pool)
+        .await
+}
+
+/// The whole blocklist, newest first.
+pub async fn list_banned(pool: &Pool<Postgres>) -> Result<Vec<BannedFile>, sqlx::Error> {
+    sqlx::query_as(
+        r#"SELECT id, sha256, reason, created_by, created_at FROM banned_files
+        ORDER BY created_at DESC, id DESC"#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Difference hash (dHash) of an image: the image is shrunk to 9x8 greyscale pixels
+/// and each of the 64 bits records whether a pixel is brighter than its right-hand
+/// neighbour. Resizing, recompression and small edits flip only a few bits, so
+/// copies of an image are a short Hamming distance apart.
+pub fn perceptual_hash(img: &DynamicImage) -> i64 {
+    let small = img.thumbnail_exact(9, 8).to_luma8();
+    let mut hash: u64 = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    // Stored in a BIGINT column; only the bit pattern matters
+    hash as i64
+}
+
+/// Perceptual hash of a stored image, for posts from before hashes were recorded.
+/// The image is turned upright first, as uploads are now.
+pub fn perceptual_hash_of_file(path: &Path) -> Option<i64> {
+    let data = std::fs::read(path).ok()?;
+    let kind = MediaKind::detect(&data)?;
+    let img = image::load_from_memory_with_format(&data, kind.image_format()?).ok()?;
+    let img = metadata::apply_orientation(img, metadata::exif_orientation(kind, &data));
+    Some(perceptual_hash(&img))
+}
+
+/// Perceptual hashes are shown and compared as 16 hex digits.
+pub fn format_phash(phash: i64) -> String {
+    format!("{:016x}", phash)
+}
+
+/// Whether a hash has enough detail to ban by. Plain images and smooth gradients
+/// hash to (nearly) all zeros or all ones, and banning one of those would refuse
+/// every other image like it.
+pub fn is_distinctive(phash: i64) -> bool {
+    let max_distance = config::get().images.phash_max_distance;
+    let ones = phash.count_ones();
+    ones > max_distance && ones < 64 - max_distance
+}
+
+/// Whether an image is within `images.phash_max_distance` bits of a banned one.
+/// The blocklist is short, so it is compared here rather than in SQL.
+pub async fn is_banned_image(pool: &Pool<Postgres>, phash: i64) -> Result<bool, sqlx::Error> {
+    let banned: Vec<i64> = sqlx::query_scalar("SELECT phash FROM banned_images")
+        .fetch_all(pool)
+        .await?;
+    let max_distance = config::get().images.phash_max_distance;
+    Ok(banned
+        .iter()
+        .any(|banned| (banned ^ phash).count_ones() <= max_distance))
+}
+
+/// Fills in the perceptual hash of a file on the posts that share it and have none.
+pub async fn record_phash(
+    pool: &Pool<Postgres>,
+    sha256: &str,
+    phash: i64,
+) -> Result<(), sqlx::Error> {
+    for table in ["threads", "replies"] {
+        sqlx::query(&format!(
+            "UPDATE {} SET media_phash = $2 WHERE media_hash = $1 AND media_phash IS NULL",
+            table
+        ))
+        .bind(sha256)
+        .bind(phash)
+        .execute(pool)
+        .await?;
+    }
+    Ok(())
+}
+
+/// The whole image blocklist, newest first.
+pub async fn list_banned_images(pool: &Pool<Postgres>) -> Result<Vec<BannedImage>, sqlx::Error> {
+    sqlx::query_as(
+        r#"SELECT id, phash, reason, created_by, created_at FROM banned_images
+        ORDER BY created_at DESC, id DESC"#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[derive(sqlx::FromRow)]
+struct StoredMedia {
+    media_url: String,
+    media_type: String,
+    thumb_url: Option<String>,
+    media_width: Option<i32>,
+    media_height: Option<i32>,
+    media_size: Option<i64>,
+    media_phash: Option<i64>,
+}
+
+/// Media already stored for an earlier post with the same contents, if its file is
+/// still on disk. The new post then points at the same file.
+pub async fn find_stored(
+    pool: &Pool<Postgres>,
+    sha256: &str,
+) -> Result<Option<SavedMedia>, sqlx::Error> {
+    // Usually one copy, but posts from before sharing (or whose file went missing)
+    // may each have their own
+    let stored: Vec<StoredMedia> = sqlx::query_as(
+        r#"SELECT media_url, media_type, thumb_url, media_width, media_height, media_size, media_phash
+        FROM threads WHERE media_hash = $1 AND media_url IS NOT NULL
+        UNION
+        SELECT media_url, media_type, thumb_url, media_width, media_height, media_size, media_phash
+        FROM replies WHERE media_hash = $1 AND media_url IS NOT NULL"#,
+    )
+    .bind(sha256)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(stored.into_iter().find_map(|stored| {
+        let on_disk = std::fs::metadata(upload::media_path(&stored.media_url)?).ok()?;
+        Some(SavedMedia {
+            url: stored.media_url,
+            media_type: stored.media_type,
+            thumb_url: stored.thumb_url,
+            width: stored.media_width,
+            height: stored.media_height,
+            size: stored.media_size.unwrap_or(on_disk.len() as i64),
+            hash: sha256.to_string(),
+            phash: stored.media_phash,
+            shared: true,
+        })
+    }))
+}
+
+/// Post number of the latest post on `board` with the same file inside the board's
+/// repost window. Always `None` on boards that allow reposts.
+pub async fn find_repost(
+    pool: &Pool<Postgres>,
+    board: &Board,
+    sha256: &str,
+    now: i64,
+) -> Result<Option<i32>, sqlx::Error> {
+    if board.repost_days <= 0 {
+        return Ok(None);
+    }
+    let since = now - i64::from(board.repost_days) * 24 * 60 * 60;
+    sqlx::query_scalar(
+        r#"SELECT post_no FROM threads
+        WHERE board_id = $1 AND media_hash = $2 AND created_at > $3
+        UNION ALL
+        SELECT r.post_no FROM replies r JOIN threads t ON t.id = r.thread_id
+        WHERE t.board_id = $1 AND r.media_hash = $2 AND r.created_at > $3
+        ORDER BY 1 DESC
+        LIMIT 1"#,
+    )
+    .bind(board.id)
+    .bind(sha256)
+    .bind(since)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Page shown when the stored copy of a file was deleted while a post using it was
+/// being saved. Posting again stores the upload afresh.
+pub fn shared_file_gone_response() -> HttpResponse {
+    HttpResponse::Conflict()
+        .content_type("text/html")
+        .body(render_error_page(
+            "Upload Failed",
+            "The file was removed while your post was being saved. Please post it again.",
+        ))
+}
+
+/// Page shown instead of accepting a repost.
+pub fn repost_response(board: &Board, post_no: i32) -> HttpResponse {
+    HttpResponse::BadRequest()
+        .content_type("text/html")
+        .body(render_error_page(
+            "Repost",
+            &format!(
+                "This file was already posted on /{}/ as No. {}. The same file cannot be posted again within {} days.",
+                board.uri, post_no, board.repost_days
+            ),
+        ))
+}
@@ -1,33 +1,50 @@
 // src/main.rs
 
+mod api;
+mod auth;
+mod bans;
 mod board; // Import the board module
-
-use board::get_board_name; // Use only the get_board_name function
+mod catalog;
+mod cli;
+mod config;
+mod csrf;
+mod feeds;
+mod hashes;
+mod markup;
+mod media;
+mod metadata;
+mod poster;
+mod ratelimit;
+mod reports;
+mod search;
+mod sniff;
+mod tripcode;
+mod upload;
+
+use auth::AdminUser;
+use board::{Board, BoardRegistry};
+use csrf::CsrfToken;
+use markup::QuoteTargets;
+use poster::PosterIdentifier;
+use ratelimit::{PostKind, RateLimiter};
+use reports::ReportTarget;
+use upload::{SavedMedia, UploadError};
 use actix_files as fs;
 use actix_multipart::Multipart;
+use actix_session::{storage::CookieSessionStore, Session, SessionMiddleware};
 use actix_web::{
-    web, App, HttpResponse, HttpServer, middleware, Error, http::header::LOCATION,
+    cookie::Key, web, App, HttpRequest, HttpResponse, HttpServer, middleware, Error,
+    http::header::LOCATION,
 };
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use futures_util::stream::StreamExt;
-use std::io::Write;
-use uuid::Uuid;
 use html_escape::encode_safe;
-use mime_guess::mime;
 use dotenv::dotenv;
+use sqlx::postgres::PgPoolOptions;
 use sqlx::{Pool, Postgres, Row, Transaction}; // Ensure Executor is imported
 
-const IMAGE_UPLOAD_DIR: &str = "./uploads/images/";
-const VIDEO_UPLOAD_DIR: &str = "./uploads/videos/";
-const IMAGE_THUMB_DIR: &str = "./thumbs/images/";
-const ADMIN_PASSWORD: &str = "af3"; // Change this to your desired admin password
-
-#[derive(Serialize, Deserialize, Clone)]
-enum MediaType {
-    Image,
-    Video,
-}
+use config::Config;
 
 #[derive(Serialize, Deserialize, Clone, sqlx::FromRow)]
 struct Thread {
@@ -35,42 +52,107 @@ struct Thread {
     board_id: i32,
     title: String,
     message: String,
+    // Poster name and tripcode; both optional, shown as Anonymous without a name
+    name: Option<String>,
+    tripcode: Option<String>,
     last_updated: i64,
     media_url: Option<String>,
     media_type: Option<String>,
+    thumb_url: Option<String>,
+    media_width: Option<i32>,
+    media_height: Option<i32>,
+    media_size: Option<i64>,
+    pinned: bool,
+    locked: bool,
+    archived: bool,
+    // Number shared with replies, unique per board; shown as "No. N" and quoted as >>N
+    post_no: i32,
 }
 
+// Column list matching the Thread struct, shared by every thread query
+const THREAD_COLUMNS: &str = "id, board_id, title, message, name, tripcode, last_updated, media_url, media_type, thumb_url, media_width, media_height, media_size, pinned, locked, archived, post_no";
+
 #[derive(Serialize, Deserialize, sqlx::FromRow)]
 struct Reply {
     id: i32,
     thread_id: i32,
     message: String,
+    name: Option<String>,
+    tripcode: Option<String>,
+    media_url: Option<String>,
+    media_type: Option<String>,
+    thumb_url: Option<String>,
+    media_width: Option<i32>,
+    media_height: Option<i32>,
+    media_size: Option<i64>,
+    post_no: i32,
 }
 
-#[derive(Serialize, Deserialize, sqlx::FromRow)]
-struct Board {
-    id: i32,
-    name: String,
-    deleted: bool,
-}
+const REPLY_COLUMNS: &str = "id, thread_id, message, name, tripcode, media_url, media_type, thumb_url, media_width, media_height, media_size, post_no";
 
 #[derive(Deserialize)]
 struct PaginationParams {
     page: Option<i32>,
 }
 
-#[derive(Deserialize)]
-struct ReplyForm {
-    thread_id: i32,
-    message: String,
+/// Threads per board index page; also used for the API and search results.
+fn threads_per_page() -> i32 {
+    config::get().limits.threads_per_page
+}
+
+/// Clamps a requested page number to the pages that exist.
+/// Returns `(page_number, total_pages)`.
+fn clamp_page(page: Option<i32>, total_threads: i64) -> (i32, i32) {
+    let total_pages = ((total_threads as f64) / (threads_per_page() as f64)).ceil() as i32;
+    let page_number = page.unwrap_or(1).max(1);
+    if total_pages > 0 && page_number > total_pages {
+        (total_pages, total_pages)
+    } else {
+        (page_number, total_pages)
+    }
 }
 
-// Removed the original get_board_name function from here
+/// Page links for a paginated listing. `base` is an escaped URL ending in `?` or `&amp;`,
+/// to which `page=N` is appended.
+fn render_pagination(base: &str, page_number: i32, total_pages: i32) -> String {
+    let mut pagination_html = String::new();
+    pagination_html.push_str(r#"<div class="pagination">"#);
+    if page_number > 1 {
+        pagination_html.push_str(&format!(
+            "<a href=\"{}page={}\">Previous</a>",
+            base,
+            page_number - 1
+        ));
+    }
+    for p in 1..=total_pages {
+        if p == page_number {
+            pagination_html.push_str(&format!("<span class=\"current\">{}</span>", p));
+        } else {
+            pagination_html.push_str(&format!("<a href=\"{}page={}\">{}</a>", base, p, p));
+        }
+    }
+    if page_number < total_pages {
+        pagination_html.push_str(&format!(
+            "<a href=\"{}page={}\">Next</a>",
+            base,
+            page_number + 1
+        ));
+    }
+    pagination_html.push_str("</div>");
+    pagination_html
+}
 
 fn escape_html(input: &str) -> String {
     encode_safe(input).to_string()
 }
 
+// Formats a stored Unix timestamp for display
+fn format_timestamp(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_default()
+}
+
 fn render_error_page(title: &str, message: &str) -> String {
     format!(
         r#"<!DOCTYPE html>
@@ -91,36 +173,121 @@ fn render_error_page(title: &str, message: &str) -> String {
     )
 }
 
-fn render_reply(reply: &Reply) -> String {
-    // Add small [x] link for deleting the reply at the bottom left
+// Images show their thumbnail and expand to the full upload when clicked
+fn render_media(
+    media_url: Option<&str>,
+    media_type: Option<&str>,
+    thumb_url: Option<&str>,
+) -> String {
+    match (media_url, media_type) {
+        (Some(url), Some("image")) => format!(
+            r#"<div class="post-media">
+<a href="{0}" target="_blank"><img src="{1}" data-thumb="{1}" data-full="{0}" alt="Post Image" class="toggle-image"></a>
+</div>"#,
+            escape_html(url),
+            escape_html(thumb_url.unwrap_or(url))
+        ),
+        (Some(url), Some(_)) => format!(
+            r#"<div class="post-media">
+<video controls class="video-player">
+    <source src="{}" type="video/mp4">
+    Your browser does not support the video tag.
+</video>
+</div>"#,
+            escape_html(url)
+        ),
+        _ => String::new(),
+    }
+}
+
+// "File: 1.2 MB, 1920x1080" for the post header. Uploads from before sizes were
+// recorded show nothing until `chess_board media details` has been run.
+fn render_file_info(
+    media_url: Option<&str>,
+    size: Option<i64>,
+    width: Option<i32>,
+    height: Option<i32>,
+) -> String {
+    let (url, size) = match (media_url, size) {
+        (Some(url), Some(size)) => (url, size),
+        _ => return String::new(),
+    };
+    let dimensions = match (width, height) {
+        (Some(width), Some(height)) => format!(", {}x{}", width, height),
+        _ => String::new(),
+    };
+    format!(
+        r#" <span class="file-info">File: <a href="{}" target="_blank">{}{}</a></span>"#,
+        escape_html(url),
+        upload::format_file_size(size.max(0) as u64),
+        dimensions
+    )
+}
+
+fn render_reply(reply: &Reply, board_uri: &str, targets: &QuoteTargets, backlinks: &str) -> String {
+    // Add small [x], [ban], [ban file] and [ban image] links for moderating the reply at the bottom left
     let admin_controls = format!(
-        r#"<a href="/admin/reply/delete/{}" class="admin-controls">[x]</a>"#,
-        reply.id
+        r#"<a href="/admin/reply/delete/{id}" class="admin-controls">[x]</a><a href="/admin/bans?reply={id}" class="admin-controls">[ban]</a>{file}"#,
+        id = reply.id,
+        file = render_media_admin_controls("reply", reply.id, reply.media_type.as_deref())
     );
 
     format!(
-        r#"<div class="post reply-post">
+        r##"<div class="post reply-post" id="p{}">
+    {}
     <div class="post-content">
         <div class="post-header">
-            <span class="title">Reply {}</span>
+            {} <a class="post-no" href="#p{}">No. {}</a> <a class="report-link" href="/report/reply/{}">Report</a>{}{}
         </div>
         <div class="message">{}</div>
         <div class="post-footer">
             {}
         </div>
     </div>
-</div>"#,
+</div>"##,
+        reply.post_no,
+        render_media(
+            reply.media_url.as_deref(),
+            reply.media_type.as_deref(),
+            reply.thumb_url.as_deref(),
+        ),
+        render_poster_name(reply.name.as_deref(), reply.tripcode.as_deref()),
+        reply.post_no,
+        reply.post_no,
         reply.id,
-        escape_html(&reply.message),
+        render_file_info(
+            reply.media_url.as_deref(),
+            reply.media_size,
+            reply.media_width,
+            reply.media_height,
+        ),
+        backlinks,
+        markup::render_message(&reply.message, board_uri, targets),
         admin_controls
     )
 }
 
-fn check_password(input: &str) -> bool {
-    input == ADMIN_PASSWORD
+// "Replies: >>N ..." links to later posts in the thread that quote this one
+fn render_backlinks(backlinks: Option<&Vec<i32>>) -> String {
+    match backlinks {
+        Some(post_nos) if !post_nos.is_empty() => format!(
+            r#" <span class="backlinks">{}</span>"#,
+            post_nos
+                .iter()
+                .map(|n| format!(r##"<a class="quotelink" href="#p{}">&gt;&gt;{}</a>"##, n, n))
+                .collect::<Vec<String>>()
+                .join(" ")
+        ),
+        _ => String::new(),
+    }
 }
 
-fn render_password_prompt(action_url: &str, title: &str, prompt: &str) -> String {
+fn render_password_prompt(
+    action_url: &str,
+    title: &str,
+    prompt: &str,
+    csrf: &CsrfToken,
+) -> String {
     format!(
         r#"<!DOCTYPE html>
 <html lang="en">
@@ -132,7 +299,9 @@ fn render_password_prompt(action_url: &str, title: &str, prompt: &str) -> String
 <body>
     <h1>{}</h1>
     <form action="{}" method="post">
+        {}
         <p>{}</p>
+        <input type="text" name="username" placeholder="Username" required>
         <input type="password" name="password" placeholder="Admin Password" required>
         <input type="submit" value="Submit">
     </form>
@@ -142,47 +311,89 @@ fn render_password_prompt(action_url: &str, title: &str, prompt: &str) -> String
         escape_html(title),
         escape_html(title),
         escape_html(action_url),
+        csrf.field(),
+        escape_html(prompt)
+    )
+}
+
+// Confirmation page for admin actions; the session already proves who is asking
+fn render_confirm_prompt(
+    action_url: &str,
+    title: &str,
+    prompt: &str,
+    csrf: &CsrfToken,
+) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>{}</title>
+    <link rel="stylesheet" href="/static/style.css">
+</head>
+<body>
+    <h1>{}</h1>
+    <form action="{}" method="post">
+        {}
+        <p>{}</p>
+        <input type="submit" value="Confirm">
+    </form>
+    <p><a href="/">[Home]</a></p>
+</body>
+</html>"#,
+        escape_html(title),
+        escape_html(title),
+        escape_html(action_url),
+        csrf.field(),
         escape_html(prompt)
     )
 }
 
 #[derive(Deserialize)]
-struct AdminActionForm {
+struct LoginForm {
+    username: String,
     password: String,
 }
 
-// Homepage
-async fn homepage(pool: web::Data<Pool<Postgres>>) -> Result<HttpResponse, Error> {
-    // Fetch all boards from the database that are not deleted
-    let boards_db = sqlx::query_as::<_, Board>(
-        "SELECT id, name, deleted FROM boards WHERE deleted = false ORDER BY id ASC",
-    )
-    .fetch_all(pool.get_ref())
-    .await
-    .map_err(actix_web::error::ErrorInternalServerError)?;
+// Where to go after a login or admin action, e.g. back to the report queue
+#[derive(Deserialize)]
+struct NextQuery {
+    next: Option<String>,
+}
 
-    // Filter boards that have a defined name in board.rs
-    let boards_defined: Vec<BoardInfoDefined> = boards_db
-        .iter()
-        .filter_map(|board| {
-            // Use get_board_name to check if the board is defined
-            get_board_name(board.id).map(|name| BoardInfoDefined {
-                id: board.id,
-                name: name.to_string(),
-            })
-        })
-        .collect();
+// Carries a validated ?next= through a confirmation form to its action
+fn next_query(next: Option<&str>) -> String {
+    match next {
+        Some(next) => format!("?next={}", urlencoding::encode(&auth::safe_next(Some(next)))),
+        None => String::new(),
+    }
+}
+
+// Homepage
+async fn homepage(registry: web::Data<BoardRegistry>) -> Result<HttpResponse, Error> {
+    // Boards come from the in-memory registry, which mirrors the boards table
+    let boards = registry.live();
 
-    let board_list_html = if boards_defined.is_empty() {
+    let board_list_html = if boards.is_empty() {
         "<p>No boards found.</p>".to_string()
     } else {
-        boards_defined
+        boards
             .into_iter()
             .map(|board| {
+                let description = if board.description.is_empty() {
+                    String::new()
+                } else {
+                    format!(
+                        r#" <span class="board-description">{}</span>"#,
+                        escape_html(&board.description)
+                    )
+                };
                 format!(
-                    r#"<p><a href="/board/{}">[{}]</a></p>"#,
-                    board.id,
-                    escape_html(&board.name)
+                    r#"<p><a href="/{}/">[/{}/ - {}]</a>{}</p>"#,
+                    escape_html(&board.uri),
+                    escape_html(&board.uri),
+                    escape_html(&board.name),
+                    description
                 )
             })
             .collect::<Vec<String>>()
@@ -201,6 +412,10 @@ async fn homepage(pool: web::Data<Pool<Postgres>>) -> Result<HttpResponse, Error
 <body>
     <div class="logo">4Chess Boards</div>
     <hr>
+    <form class="search-form" action="/search" method="get">
+        <input type="search" name="q" maxlength="200" placeholder="Search posts" required>
+        <input type="submit" value="Search">
+    </form>
     <h2>Available Boards</h2>
     {}
 </body>
@@ -211,59 +426,90 @@ async fn homepage(pool: web::Data<Pool<Postgres>>) -> Result<HttpResponse, Error
     Ok(HttpResponse::Ok().content_type("text/html").body(html))
 }
 
-struct BoardInfoDefined {
-    id: i32,
-    name: String,
+fn board_not_found() -> HttpResponse {
+    HttpResponse::NotFound()
+        .content_type("text/html")
+        .body(render_error_page("Not Found", "Board does not exist or has been deleted."))
 }
 
 // Board page
 async fn board_page(
     pool: web::Data<Pool<Postgres>>,
+    registry: web::Data<BoardRegistry>,
     path: web::Path<(i32,)>,
     query: web::Query<PaginationParams>,
+    csrf: CsrfToken,
 ) -> Result<HttpResponse, Error> {
     let board_id = path.into_inner().0;
 
-    // Verify if the board is defined using get_board_name
-    let board_name = match get_board_name(board_id) {
-        Some(name) => name,
-        None => {
-            return Ok(HttpResponse::NotFound().body(
-                render_error_page("Not Found", "Board does not exist or has been deleted."),
-            ));
+    match registry.get(board_id) {
+        Some(board) => {
+            render_board_page(pool.get_ref(), &registry, &board, query.page, &csrf).await
         }
-    };
+        None => Ok(board_not_found()),
+    }
+}
 
-    let page_size = 10;
-    let page_number = query.page.unwrap_or(1).max(1);
+// Board page by short URI, e.g. /kg/
+async fn board_page_by_uri(
+    pool: web::Data<Pool<Postgres>>,
+    registry: web::Data<BoardRegistry>,
+    path: web::Path<(String,)>,
+    query: web::Query<PaginationParams>,
+    csrf: CsrfToken,
+) -> Result<HttpResponse, Error> {
+    let uri = path.into_inner().0;
 
-    let total_threads: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM threads WHERE board_id = $1")
-        .bind(board_id)
-        .fetch_one(pool.get_ref())
-        .await
-        .map_err(actix_web::error::ErrorInternalServerError)?;
+    match registry.get_by_uri(&uri) {
+        Some(board) => {
+            render_board_page(pool.get_ref(), &registry, &board, query.page, &csrf).await
+        }
+        None => Ok(board_not_found()),
+    }
+}
 
-    let total_pages = ((total_threads as f64) / (page_size as f64)).ceil() as i32;
-    let page_number = if total_pages > 0 && page_number > total_pages {
-        total_pages
-    } else {
-        page_number
-    };
-    let offset = (page_number - 1) * page_size;
+async fn render_board_page(
+    pool: &Pool<Postgres>,
+    registry: &BoardRegistry,
+    board: &Board,
+    page: Option<i32>,
+    csrf: &CsrfToken,
+) -> Result<HttpResponse, Error> {
+    let board_id = board.id;
+
+    let total_threads: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM threads WHERE board_id = $1 AND NOT archived")
+            .bind(board_id)
+            .fetch_one(pool)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let (page_number, total_pages) = clamp_page(page, total_threads);
+    let offset = (page_number - 1) * threads_per_page();
 
-    let threads = sqlx::query_as::<_, Thread>(
+    let threads = sqlx::query_as::<_, Thread>(&format!(
         r#"
-        SELECT id, board_id, title, message, last_updated, media_url, media_type
+        SELECT {}
         FROM threads
-        WHERE board_id = $1
-        ORDER BY last_updated DESC
+        WHERE board_id = $1 AND NOT archived
+        ORDER BY pinned DESC, last_updated DESC
         LIMIT $2 OFFSET $3
         "#,
-    )
+        THREAD_COLUMNS
+    ))
     .bind(board_id)
-    .bind(page_size)
+    .bind(threads_per_page())
     .bind(offset)
-    .fetch_all(pool.get_ref())
+    .fetch_all(pool)
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let targets = QuoteTargets::resolve(
+        pool,
+        registry,
+        &board.uri,
+        threads.iter().map(|t| t.message.as_str()),
+    )
     .await
     .map_err(actix_web::error::ErrorInternalServerError)?;
 
@@ -271,39 +517,14 @@ async fn board_page(
         "<p>No threads found. Create one!</p>".to_string()
     } else {
         threads
-            .into_iter()
-            .map(|t| render_thread(&t))
+            .iter()
+            .map(|t| render_thread(t, &board.uri, &targets))
             .collect::<Vec<String>>()
             .join("<hr>")
     };
 
-    let mut pagination_html = String::new();
-    pagination_html.push_str(r#"<div class="pagination">"#);
-    if page_number > 1 {
-        pagination_html.push_str(&format!(
-            "<a href=\"/board/{}?page={}\">Previous</a>",
-            board_id,
-            page_number - 1
-        ));
-    }
-    for p in 1..=total_pages {
-        if p == page_number {
-            pagination_html.push_str(&format!("<span class=\"current\">{}</span>", p));
-        } else {
-            pagination_html.push_str(&format!(
-                "<a href=\"/board/{}?page={}\">{}</a>",
-                board_id, p, p
-            ));
-        }
-    }
-    if page_number < total_pages {
-        pagination_html.push_str(&format!(
-            "<a href=\"/board/{}?page={}\">Next</a>",
-            board_id,
-            page_number + 1
-        ));
-    }
-    pagination_html.push_str("</div>");
+    let pagination_html =
+        render_pagination(&format!("/{}/?", escape_html(&board.uri)), page_number, total_pages);
 
     let html = format!(
         r#"<!DOCTYPE html>
@@ -312,17 +533,21 @@ async fn board_page(
     <meta charset="UTF-8">
     <title>{}</title>
     <link rel="stylesheet" href="/static/style.css">
+    {}
     <script defer src="/static/script.js"></script>
 </head>
 <body>
     <div class="navigation-board">
         <hr class="hr-green">
-        <a href="/">[Home]</a>
+        <a href="/">[Home]</a> <a href="/board/{}/catalog">[Catalog]</a> <a href="/search?board={}">[Search]</a>{}
     </div>
-    <h2>{}</h2>
+    <h2>/{}/ - {}</h2>
+    {}
     <form class="postform" action="/board/{}/thread" method="post" enctype="multipart/form-data">
-        <input type="text" id="title" name="title" maxlength="75" placeholder="Title" required>
-        <textarea id="message" name="message" rows="4" maxlength="8000" placeholder="Message" required></textarea>
+        {}
+        <input type="text" id="title" name="title" maxlength="{}" placeholder="Title" required>
+        <input type="text" id="name" name="name" maxlength="{}" placeholder="Name (optional; name#secret for a tripcode)">
+        <textarea id="message" name="message" rows="4" maxlength="{}" placeholder="Message" required></textarea>
         <label>Upload Media (JPEG, PNG, GIF, WEBP, MP4):</label>
         <input type="file" name="media" accept=".jpg,.jpeg,.png,.gif,.webp,.mp4">
         <input type="submit" value="Create Thread">
@@ -332,9 +557,19 @@ async fn board_page(
     {}
 </body>
 </html>"#,
-        escape_html(board_name),
-        escape_html(board_name),
+        escape_html(&board.name),
+        feeds::alternate_link(&feeds::board_feed_url(board), &format!("/{}/ threads", board.uri)),
         board_id,
+        escape_html(&board.uri),
+        render_archive_link(board),
+        escape_html(&board.uri),
+        escape_html(&board.name),
+        render_board_description(board),
+        board_id,
+        csrf.field(),
+        config::get().limits.title_max_len,
+        name_field_max_len(),
+        config::get().limits.message_max_len,
         thread_list_html,
         pagination_html
     );
@@ -342,46 +577,108 @@ async fn board_page(
     Ok(HttpResponse::Ok().content_type("text/html").body(html))
 }
 
-fn render_thread(thread: &Thread) -> String {
-    let media_html = if let Some(url) = &thread.media_url {
-        if let Some(mt) = &thread.media_type {
-            if mt == "image" {
-                format!(
-                    r#"<div class="post-media">
-<img src="{}" alt="Thread Image" class="toggle-image">
-</div>"#,
-                    escape_html(url)
-                )
-            } else {
+fn render_archive_link(board: &Board) -> String {
+    if board.archive_pruned {
+        format!(r#" <a href="/board/{}/archive">[Archive]</a>"#, board.id)
+    } else {
+        String::new()
+    }
+}
+
+// Lists a board's archived threads, most recently bumped first
+async fn board_archive(
+    pool: web::Data<Pool<Postgres>>,
+    registry: web::Data<BoardRegistry>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    let board = match registry.get(path.into_inner()) {
+        Some(board) => board,
+        None => return Ok(board_not_found()),
+    };
+
+    let threads: Vec<(i32, String, i64)> = sqlx::query_as(
+        r#"SELECT id, title, last_updated FROM threads
+        WHERE board_id = $1 AND archived
+        ORDER BY last_updated DESC"#,
+    )
+    .bind(board.id)
+    .fetch_all(pool.get_ref())
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let rows = if threads.is_empty() {
+        "<p>No archived threads.</p>".to_string()
+    } else {
+        let rows = threads
+            .iter()
+            .map(|(id, title, last_updated)| {
                 format!(
-                    r#"<div class="post-media">
-<video controls class="video-player">
-    <source src="{}" type="video/mp4">
-    Your browser does not support the video tag.
-</video>
-</div>"#,
-                    escape_html(url)
+                    r#"<tr><td><a href="/thread/{}">{}</a></td><td>{}</td></tr>"#,
+                    id,
+                    escape_html(title),
+                    format_timestamp(*last_updated)
                 )
-            }
-        } else {
-            "".to_string()
-        }
-    } else {
-        "".to_string()
+            })
+            .collect::<String>();
+        format!(
+            r#"<table class="admin-table"><tr><th>Thread</th><th>Last bumped</th></tr>{}</table>"#,
+            rows
+        )
     };
 
-    // Add [x] for deletion at the bottom left
-    let admin_controls = format!(
-        r#"<a href="/admin/thread/delete/{}" class="admin-controls">[x]</a>"#,
-        thread.id
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>{} - Archive</title>
+    <link rel="stylesheet" href="/static/style.css">
+</head>
+<body>
+    <div class="navigation-board">
+        <hr class="hr-green">
+        <a href="/">[Home]</a> <a href="/{}/">[Return]</a>
+    </div>
+    <h2>/{}/ - Archive</h2>
+    {}
+</body>
+</html>"#,
+        escape_html(&board.name),
+        escape_html(&board.uri),
+        escape_html(&board.uri),
+        rows
+    );
+
+    Ok(HttpResponse::Ok().content_type("text/html").body(html))
+}
+
+fn render_board_description(board: &Board) -> String {
+    if board.description.is_empty() {
+        String::new()
+    } else {
+        format!(
+            r#"<p class="board-description">{}</p>"#,
+            escape_html(&board.description)
+        )
+    }
+}
+
+fn render_thread(thread: &Thread, board_uri: &str, targets: &QuoteTargets) -> String {
+    let media_html = render_media(
+        thread.media_url.as_deref(),
+        thread.media_type.as_deref(),
+        thread.thumb_url.as_deref(),
     );
 
+    // Admin controls (delete, pin, lock) at the bottom left
+    let admin_controls = render_thread_admin_controls(thread);
+
     format!(
         r#"<div class="post thread-post">
 {}
 <div class="post-content">
     <div class="post-header">
-        <span class="title">{}</span> <a class="reply-link" href="/thread/{}">Reply</a>
+        <span class="title">{}{}</span> {} <a class="post-no" href="/thread/{}#p{}">No. {}</a> <a class="reply-link" href="/thread/{}">Reply</a> <a class="report-link" href="/report/thread/{}">Report</a>{}
     </div>
     <div class="message">{}</div>
     <div class="post-footer">
@@ -390,23 +687,95 @@ fn render_thread(thread: &Thread) -> String {
 </div>
 </div>"#,
         media_html,
+        render_thread_badges(thread),
         escape_html(&thread.title),
+        render_poster_name(thread.name.as_deref(), thread.tripcode.as_deref()),
         thread.id,
-        escape_html(&thread.message),
+        thread.post_no,
+        thread.post_no,
+        thread.id,
+        thread.id,
+        render_thread_file_info(thread),
+        markup::render_message(&thread.message, board_uri, targets),
         admin_controls
     )
 }
 
+// Poster name and tripcode for a post header
+fn render_poster_name(name: Option<&str>, tripcode: Option<&str>) -> String {
+    let tripcode = tripcode
+        .map(|tripcode| format!(r#"<span class="tripcode">{}</span>"#, escape_html(tripcode)))
+        .unwrap_or_default();
+    format!(
+        r#"<span class="name">{}</span>{}"#,
+        escape_html(name.unwrap_or(tripcode::DEFAULT_NAME)),
+        tripcode
+    )
+}
+
+fn render_thread_file_info(thread: &Thread) -> String {
+    render_file_info(
+        thread.media_url.as_deref(),
+        thread.media_size,
+        thread.media_width,
+        thread.media_height,
+    )
+}
+
+fn render_thread_badges(thread: &Thread) -> String {
+    let mut badges = String::new();
+    if thread.pinned {
+        badges.push_str(r#"<span class="badge badge-pinned">Pinned</span> "#);
+    }
+    if thread.archived {
+        badges.push_str(r#"<span class="badge badge-archived">Archived</span> "#);
+    } else if thread.locked {
+        badges.push_str(r#"<span class="badge badge-locked">Locked</span> "#);
+    }
+    badges
+}
+
+fn render_thread_admin_controls(thread: &Thread) -> String {
+    let pin_action = if thread.pinned { "unpin" } else { "pin" };
+    let lock_action = if thread.locked { "unlock" } else { "lock" };
+    format!(
+        r#"<a href="/admin/thread/delete/{id}" class="admin-controls">[x]</a><a href="/admin/thread/{pin}/{id}" class="admin-controls">[{pin}]</a><a href="/admin/thread/{lock}/{id}" class="admin-controls">[{lock}]</a><a href="/admin/bans?thread={id}" class="admin-controls">[ban]</a>{file}"#,
+        id = thread.id,
+        pin = pin_action,
+        lock = lock_action,
+        file = render_media_admin_controls("thread", thread.id, thread.media_type.as_deref())
+    )
+}
+
+// [ban file] for any upload, plus [ban image] for images, which can be matched by
+// perceptual hash
+fn render_media_admin_controls(kind: &str, id: i32, media_type: Option<&str>) -> String {
+    match media_type {
+        Some("image") => format!(
+            r#"<a href="/admin/files?{kind}={id}" class="admin-controls">[ban file]</a><a href="/admin/ban-image/{kind}/{id}" class="admin-controls">[ban image]</a>"#,
+            kind = kind,
+            id = id
+        ),
+        Some(_) => format!(
+            r#"<a href="/admin/files?{}={}" class="admin-controls">[ban file]</a>"#,
+            kind, id
+        ),
+        None => String::new(),
+    }
+}
+
 // View a single thread
 async fn view_thread(
     pool: web::Data<Pool<Postgres>>,
+    registry: web::Data<BoardRegistry>,
     path: web::Path<(i32,)>,
+    csrf: CsrfToken,
 ) -> Result<HttpResponse, Error> {
     let thread_id = path.into_inner().0;
-    let thread: Option<Thread> = sqlx::query_as(
-        r#"SELECT id, board_id, title, message, last_updated, media_url, media_type
-        FROM threads WHERE id = $1"#,
-    )
+    let thread: Option<Thread> = sqlx::query_as(&format!(
+        "SELECT {} FROM threads WHERE id = $1",
+        THREAD_COLUMNS
+    ))
     .bind(thread_id)
     .fetch_optional(pool.get_ref())
     .await
@@ -420,93 +789,104 @@ async fn view_thread(
 
     let thread = thread.unwrap();
 
-    let replies = sqlx::query_as::<_, Reply>(
-        "SELECT id, thread_id, message FROM replies WHERE thread_id = $1 ORDER BY id ASC",
-    )
+    // Threads on deleted boards are hidden along with the board
+    let board = match registry.get(thread.board_id) {
+        Some(board) => board,
+        None => return Ok(board_not_found()),
+    };
+
+    let replies = sqlx::query_as::<_, Reply>(&format!(
+        "SELECT {} FROM replies WHERE thread_id = $1 ORDER BY id ASC",
+        REPLY_COLUMNS
+    ))
     .bind(thread_id)
     .fetch_all(pool.get_ref())
     .await
     .map_err(actix_web::error::ErrorInternalServerError)?;
 
+    let messages = std::iter::once(thread.message.as_str())
+        .chain(replies.iter().map(|r| r.message.as_str()));
+    let targets = QuoteTargets::resolve(pool.get_ref(), &registry, &board.uri, messages)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let posts: Vec<(i32, &str)> = std::iter::once((thread.post_no, thread.message.as_str()))
+        .chain(replies.iter().map(|r| (r.post_no, r.message.as_str())))
+        .collect();
+    let backlinks = markup::backlinks(&board.uri, &posts);
+
     let replies_html = if replies.is_empty() {
         "<p>No replies yet.</p>".to_string()
     } else {
         replies
-            .into_iter()
-            .map(|r| render_reply(&r))
+            .iter()
+            .map(|r| {
+                render_reply(
+                    r,
+                    &board.uri,
+                    &targets,
+                    &render_backlinks(backlinks.get(&r.post_no)),
+                )
+            })
             .collect::<Vec<String>>()
             .join("<hr>")
     };
 
-    let media_html = if let Some(url) = &thread.media_url {
-        if let Some(mt) = &thread.media_type {
-            if mt == "image" {
-                format!(
-                    r#"<div class="post-media">
-<img src="{}" alt="Thread Image" class="toggle-image">
-</div>"#,
-                    escape_html(url)
-                )
-            } else {
-                format!(
-                    r#"<div class="post-media">
-<video controls class="video-player">
-    <source src="{}" type="video/mp4">
-    Your browser does not support the video tag.
-</video>
-</div>"#,
-                    escape_html(url)
-                )
-            }
-        } else {
-            "".to_string()
-        }
-    } else {
-        "".to_string()
-    };
+    let media_html = render_media(
+        thread.media_url.as_deref(),
+        thread.media_type.as_deref(),
+        thread.thumb_url.as_deref(),
+    );
 
-    let reply_form = format!(
-        r#"<form class="postform" action="/reply" method="post">
+    let reply_form = if thread.archived {
+        r#"<p class="locked-notice">This thread is archived. New replies are not accepted.</p>"#
+            .to_string()
+    } else if thread.locked {
+        r#"<p class="locked-notice">This thread is locked. New replies are not accepted.</p>"#
+            .to_string()
+    } else {
+        format!(
+            r#"<form class="postform" action="/reply" method="post" enctype="multipart/form-data">
+{}
 <input type="hidden" name="thread_id" value="{}">
-<textarea name="message" rows="4" maxlength="8000" placeholder="Message" required></textarea>
+<input type="text" name="name" maxlength="{}" placeholder="Name (optional; name#secret for a tripcode)">
+<textarea name="message" rows="4" maxlength="{}" placeholder="Message" required></textarea>
+<label>Upload Media (JPEG, PNG, GIF, WEBP, MP4):</label>
+<input type="file" name="media" accept=".jpg,.jpeg,.png,.gif,.webp,.mp4">
+<label><input type="checkbox" name="sage"> Sage (do not bump the thread)</label>
 <input type="submit" value="Reply">
 </form>"#,
-        thread_id
-    );
-
-    let admin_controls = format!(
-        r#" <a href="/admin/thread/delete/{}" class="admin-controls">[x]</a>"#,
-        thread_id
-    );
-
-    // Fetch the board ID to create the back to board link
-    let board_id = thread.board_id;
-    let board_name = match get_board_name(board_id) {
-        Some(name) => name,
-        None => "Unknown Board",
+            csrf.field(),
+            thread_id,
+            name_field_max_len(),
+            config::get().limits.message_max_len
+        )
     };
 
+    let admin_controls = render_thread_admin_controls(&thread);
+
     let html = format!(
-        r#"<!DOCTYPE html>
+        r##"<!DOCTYPE html>
 <html lang="en">
 <head>
     <meta charset="UTF-8">
     <title>{} - {}</title>
     <link rel="stylesheet" href="/static/style.css">
+    {}
     <script defer src="/static/script.js"></script>
 </head>
 <body>
     <div class="navigation-reply">
         <hr>
-        <a href="/board/{}">Back to Board</a> | <a href="/">[Home]</a>
+        <a href="/{}/">Back to Board</a> | <a href="/">[Home]</a>
     </div>
     <h2>{}</h2>
     {}
-    <div class="post thread-post">
+    <div class="post thread-post" id="p{}">
         {}
         <div class="post-content">
             <div class="post-header">
-                <span class="title">{}</span> <a class="reply-link" href="/thread/{}">Reply</a>
+                <span class="title">{}{}</span> {} <a class="post-no" href="#p{}">No. {}</a> <a class="reply-link" href="/thread/{}">Reply</a> <a class="report-link" href="/report/thread/{}">Report</a>{}{}
             </div>
             <div class="message">{}</div>
             <div class="post-footer">
@@ -521,16 +901,25 @@ async fn view_thread(
         {}
     </div>
 </body>
-</html>"#,
-        escape_html(board_name),            // Using board_name in the title
+</html>"##,
+        escape_html(&board.name),            // Using board name in the title
         escape_html(&thread.title),
-        board_id,
+        feeds::alternate_link(&feeds::thread_feed_url(thread.id), "Replies"),
+        escape_html(&board.uri),
         escape_html(&thread.title),
         media_html,
+        thread.post_no,
         "",
+        render_thread_badges(&thread),
         escape_html(&thread.title),
+        render_poster_name(thread.name.as_deref(), thread.tripcode.as_deref()),
+        thread.post_no,
+        thread.post_no,
+        thread.id,
         thread.id,
-        escape_html(&thread.message),
+        render_thread_file_info(&thread),
+        render_backlinks(backlinks.get(&thread.post_no)),
+        markup::render_message(&thread.message, &board.uri, &targets),
         reply_form,
         admin_controls,
         replies_html
@@ -540,132 +929,178 @@ async fn view_thread(
 }
 
 async fn create_thread(
+    req: HttpRequest,
     pool: web::Data<Pool<Postgres>>,
+    registry: web::Data<BoardRegistry>,
+    identifier: web::Data<PosterIdentifier>,
+    limiter: web::Data<RateLimiter>,
     board_id: web::Path<(i32,)>,
     mut payload: Multipart,
 ) -> Result<HttpResponse, Error> {
     let board_id = board_id.into_inner().0;
 
-    // Verify if the board is defined
-    if get_board_name(board_id).is_none() {
-        return Ok(HttpResponse::NotFound().body(
-            render_error_page("Not Found", "Board does not exist or has been deleted."),
-        ));
+    // Verify the board exists and is not deleted
+    let board = match registry.get(board_id) {
+        Some(board) => board,
+        None => return Ok(board_not_found()),
+    };
+
+    // The board is in the path, so banned posters are refused before their upload is
+    // read. Replies name their thread in the form and are checked after it.
+    let poster = identifier.identify(&req)?;
+    if let Some(ban) = bans::find_active(pool.get_ref(), &poster, board_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+    {
+        return Ok(bans::banned_response(&ban, &registry));
     }
 
     let mut title = String::new();
+    let mut name_field = String::new();
     let mut message = String::new();
-    let mut media_url: Option<String> = None;
-    let mut media_type: Option<String> = None;
+    let mut media: Option<SavedMedia> = None;
 
     // Handling multipart form data
     while let Some(item) = payload.next().await {
-        let mut field = item.map_err(actix_web::error::ErrorInternalServerError)?;
-        let cd = field.content_disposition();
+        let mut field = match item {
+            Ok(field) => field,
+            Err(e) => {
+                upload::discard(media);
+                return Err(actix_web::error::ErrorInternalServerError(e));
+            }
+        };
 
-        let name = if let Some(name) = cd.get_name() {
-            name
-        } else {
-            continue;
+        let name = match field.content_disposition().get_name() {
+            Some(name) => name.to_string(),
+            None => continue,
         };
 
-        match name {
-            "title" => {
-                while let Some(chunk) = field.next().await {
-                    let data = chunk.map_err(actix_web::error::ErrorInternalServerError)?;
-                    title.push_str(&String::from_utf8_lossy(&data));
-                }
-            }
-            "message" => {
-                while let Some(chunk) = field.next().await {
-                    let data = chunk.map_err(actix_web::error::ErrorInternalServerError)?;
-                    message.push_str(&String::from_utf8_lossy(&data));
-                }
+        let result = match name.as_str() {
+            "title" => upload::read_text_field(&mut field, text_field_limit())
+                .await
+                .map(|value| title = value),
+            "name" => upload::read_text_field(&mut field, text_field_limit())
+                .await
+                .map(|value| name_field = value),
+            "message" => upload::read_text_field(&mut field, text_field_limit())
+                .await
+                .map(|value| message = value),
+            "media" => upload::save_media_field(&mut field, pool.get_ref()).await.map(|saved| {
+                upload::discard(media.take());
+                media = saved;
+            }),
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            upload::discard(media);
+            return upload_error_response(e);
+        }
+    }
+
+    // Everything from here to the commit runs with the upload on disk, so any refusal
+    // or error discards it in one place
+    let result: Result<i32, Error> = async {
+        if title.trim().is_empty() || message.trim().is_empty() {
+            return Err(refused(HttpResponse::BadRequest()
+                .content_type("text/html")
+                .body(render_error_page(
+                    "Bad Request",
+                    "Title and Message cannot be empty",
+                ))));
+        }
+        let limits = &config::get().limits;
+        let signature = identifier.sign(&name_field);
+        let name = signature.name.as_deref().unwrap_or_default();
+        if let Some(response) = length_error("Title", &title, limits.title_max_len)
+            .or_else(|| length_error("Name", name, limits.name_max_len))
+            .or_else(|| length_error("Message", &message, limits.message_max_len))
+        {
+            return Err(refused(response));
+        }
+
+        let now = Utc::now().timestamp();
+
+        let duplicate =
+            ratelimit::is_duplicate(pool.get_ref(), &board, &poster.hash, message.trim(), now)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+        if duplicate {
+            return Err(refused(ratelimit::duplicate_response()));
+        }
+
+        if let Some(saved) = &media {
+            let repost = hashes::find_repost(pool.get_ref(), &board, &saved.hash, now)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+            if let Some(post_no) = repost {
+                return Err(refused(hashes::repost_response(&board, post_no)));
             }
-            "media" => {
-                if let Some(filename) = cd.get_filename() {
-                    if !filename.trim().is_empty() {
-                        let mime_type = mime_guess::from_path(&filename).first_or_octet_stream();
-                        if mime_type.type_() == mime::IMAGE {
-                            let extension = mime_type.subtype().as_str();
-                            if !matches!(extension, "jpeg" | "png" | "gif" | "webp") {
-                                return Ok(HttpResponse::BadRequest()
-                                    .body("Unsupported image format"));
-                            }
-
-                            let unique_id = Uuid::new_v4().to_string();
-                            let sanitized_filename = format!("{}.{}", unique_id, extension);
-                            let filepath = format!("{}{}", IMAGE_UPLOAD_DIR, sanitized_filename);
-                            let mut f = std::fs::File::create(&filepath)
-                                .map_err(actix_web::error::ErrorInternalServerError)?;
-                            while let Some(chunk) = field.next().await {
-                                let data =
-                                    chunk.map_err(actix_web::error::ErrorInternalServerError)?;
-                                f.write_all(&data)
-                                    .map_err(actix_web::error::ErrorInternalServerError)?;
-                            }
-
-                            if image::open(&filepath).is_err() {
-                                std::fs::remove_file(&filepath).ok();
-                                return Ok(HttpResponse::BadRequest()
-                                    .body("Invalid image file"));
-                            }
-
-                            media_url = Some(format!("/uploads/images/{}", sanitized_filename));
-                            media_type = Some("image".to_string());
-                        } else if mime_type.type_() == mime::VIDEO {
-                            let extension = mime_type.subtype().as_str();
-                            if extension != "mp4" {
-                                return Ok(HttpResponse::BadRequest()
-                                    .body("Unsupported video format"));
-                            }
-                            let unique_id = Uuid::new_v4().to_string();
-                            let sanitized_filename = format!("{}.mp4", unique_id);
-                            let filepath = format!("{}{}", VIDEO_UPLOAD_DIR, sanitized_filename);
-                            let mut f = std::fs::File::create(&filepath)
-                                .map_err(actix_web::error::ErrorInternalServerError)?;
-                            while let Some(chunk) = field.next().await {
-                                let data =
-                                    chunk.map_err(actix_web::error::ErrorInternalServerError)?;
-                                f.write_all(&data)
-                                    .map_err(actix_web::error::ErrorInternalServerError)?;
-                            }
-                            media_url = Some(format!("/uploads/videos/{}", sanitized_filename));
-                            media_type = Some("video".to_string());
-                        }
-                    }
-                }
+        }
+
+        // Only a post that passed every check uses up the poster's cooldown
+        if let Err(wait) = limiter.acquire(poster.ip, &board, PostKind::Thread) {
+            return Err(refused(ratelimit::rate_limited_response(PostKind::Thread, wait)));
+        }
+
+        let mut tx: Transaction<'_, Postgres> = pool
+            .begin()
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+
+        if let Some(saved) = &media {
+            let present = media::claim_shared(&mut tx, saved)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+            if !present {
+                return Err(refused(hashes::shared_file_gone_response()));
             }
-            _ => {}
         }
-    }
 
-    if title.trim().is_empty() || message.trim().is_empty() {
-        return Ok(HttpResponse::BadRequest()
-            .content_type("text/html")
-            .body(render_error_page(
-                "Bad Request",
-                "Title and Message cannot be empty",
-            )));
-    }
+        let post_no = next_post_no(&mut tx, board_id)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    let now = Utc::now().timestamp();
+        let record = sqlx::query(
+            "INSERT INTO threads (board_id, title, message, name, tripcode, last_updated, created_at, media_url, media_type, thumb_url, media_width, media_height, media_size, media_hash, media_phash, poster_hash, post_no) VALUES ($1, $2, $3, $4, $5, $6, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16) RETURNING id",
+        )
+        .bind(board_id)
+        .bind(title.trim())
+        .bind(message.trim())
+        .bind(&signature.name)
+        .bind(&signature.tripcode)
+        .bind(now)
+        .bind(media.as_ref().map(|m| m.url.clone()))
+        .bind(media.as_ref().map(|m| m.media_type.clone()))
+        .bind(media.as_ref().and_then(|m| m.thumb_url.clone()))
+        .bind(media.as_ref().and_then(|m| m.width))
+        .bind(media.as_ref().and_then(|m| m.height))
+        .bind(media.as_ref().map(|m| m.size))
+        .bind(media.as_ref().map(|m| m.hash.clone()))
+        .bind(media.as_ref().and_then(|m| m.phash))
+        .bind(&poster.hash)
+        .bind(post_no)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    let record = sqlx::query(
-        "INSERT INTO threads (board_id, title, message, last_updated, media_url, media_type) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id",
-    )
-    .bind(board_id)
-    .bind(title.trim())
-    .bind(message.trim())
-    .bind(now)
-    .bind(media_url)
-    .bind(media_type)
-    .fetch_one(pool.get_ref())
-    .await
-    .map_err(actix_web::error::ErrorInternalServerError)?;
+        let id: i32 = record
+            .try_get("id")
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+
+        tx.commit()
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        Ok(id)
+    }
+    .await;
+    if result.is_err() {
+        upload::discard(media);
+    }
+    let id = result?;
 
-    let id: i32 = record
-        .try_get("id")
+    // Keep the board within its thread limit; the new thread itself always stays
+    media::prune_board(pool.get_ref(), &board, id)
+        .await
         .map_err(actix_web::error::ErrorInternalServerError)?;
 
     Ok(HttpResponse::SeeOther()
@@ -673,226 +1108,1768 @@ async fn create_thread(
         .finish())
 }
 
+// Turns a rejected or failed upload into the response for the poster
+fn upload_error_response(err: UploadError) -> Result<HttpResponse, Error> {
+    match err {
+        UploadError::Rejected(message) => Ok(HttpResponse::BadRequest()
+            .content_type("text/html")
+            .body(render_error_page("Bad Request", message))),
+        UploadError::TooLarge(limit) => Ok(HttpResponse::PayloadTooLarge()
+            .content_type("text/html")
+            .body(render_error_page(
+                "File Too Large",
+                &format!(
+                    "The file is too large. The limit is {}.",
+                    upload::format_file_size(limit)
+                ),
+            ))),
+        UploadError::Internal(e) => Err(actix_web::error::ErrorInternalServerError(e)),
+    }
+}
+
+// A page refusing a post, as an error so the post handlers can return it with `?`
+// and discard the upload in one place
+fn refused(response: HttpResponse) -> Error {
+    actix_web::error::InternalError::from_response("post refused", response).into()
+}
+
+// Most bytes a text field can hold: the longest allowed title, name or message at
+// four bytes per character. Longer fields are refused while they are still being read.
+fn text_field_limit() -> usize {
+    let limits = &config::get().limits;
+    limits
+        .title_max_len
+        .max(limits.message_max_len)
+        .max(name_field_max_len())
+        * 4
+}
+
+// Length allowed in the name inputs: the name itself plus room for a tripcode secret
+fn name_field_max_len() -> usize {
+    config::get().limits.name_max_len + 64
+}
+
+// Checks a title, name or message against its limit, counting characters after trimming.
+// Returns the error page when it is too long.
+fn length_error(what: &str, value: &str, max: usize) -> Option<HttpResponse> {
+    if value.trim().chars().count() <= max {
+        return None;
+    }
+    Some(HttpResponse::BadRequest()
+        .content_type("text/html")
+        .body(render_error_page(
+            "Bad Request",
+            &format!("{} is too long. The limit is {} characters.", what, max),
+        )))
+}
+
 // Create a reply
 async fn create_reply(
+    req: HttpRequest,
     pool: web::Data<Pool<Postgres>>,
-    form: web::Form<ReplyForm>,
+    registry: web::Data<BoardRegistry>,
+    identifier: web::Data<PosterIdentifier>,
+    limiter: web::Data<RateLimiter>,
+    mut payload: Multipart,
 ) -> Result<HttpResponse, Error> {
     use sqlx::Executor; // Re-import Executor inside the function scope
 
-    let message = form.message.trim();
-    if message.is_empty() {
-        return Ok(HttpResponse::BadRequest().body("Message cannot be empty"));
+    let mut thread_id_field = String::new();
+    let mut name_field = String::new();
+    let mut message = String::new();
+    let mut sage = false;
+    let mut media: Option<SavedMedia> = None;
+
+    while let Some(item) = payload.next().await {
+        let mut field = match item {
+            Ok(field) => field,
+            Err(e) => {
+                upload::discard(media);
+                return Err(actix_web::error::ErrorInternalServerError(e));
+            }
+        };
+
+        let name = match field.content_disposition().get_name() {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+
+        let result = match name.as_str() {
+            "thread_id" => upload::read_text_field(&mut field, text_field_limit())
+                .await
+                .map(|value| thread_id_field = value),
+            "name" => upload::read_text_field(&mut field, text_field_limit())
+                .await
+                .map(|value| name_field = value),
+            "message" => upload::read_text_field(&mut field, text_field_limit())
+                .await
+                .map(|value| message = value),
+            "sage" => upload::read_text_field(&mut field, text_field_limit())
+                .await
+                .map(|_| sage = true),
+            "media" => upload::save_media_field(&mut field, pool.get_ref()).await.map(|saved| {
+                upload::discard(media.take());
+                media = saved;
+            }),
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            upload::discard(media);
+            return upload_error_response(e);
+        }
     }
 
-    let thread_id = form.thread_id;
-    let now = Utc::now().timestamp();
+    // Everything from here to the commit runs with the upload on disk, so any refusal
+    // or error discards it in one place
+    let result: Result<(i32, i32), Error> = async {
+        let message = message.trim();
+        if message.is_empty() {
+            return Err(refused(HttpResponse::BadRequest().body("Message cannot be empty")));
+        }
+        let limits = &config::get().limits;
+        let signature = identifier.sign(&name_field);
+        if let Some(response) =
+            length_error("Name", signature.name.as_deref().unwrap_or_default(), limits.name_max_len)
+                .or_else(|| length_error("Message", message, limits.message_max_len))
+        {
+            return Err(refused(response));
+        }
 
-    // Begin a transaction
-    let mut tx: Transaction<'_, Postgres> = pool
-        .begin()
-        .await
-        .map_err(actix_web::error::ErrorInternalServerError)?;
+        let thread_id: i32 = match thread_id_field.trim().parse() {
+            Ok(id) => id,
+            Err(_) => return Err(refused(HttpResponse::BadRequest().body("Invalid thread"))),
+        };
 
-    // Insert the reply
-    tx.execute(
-        sqlx::query("INSERT INTO replies (thread_id, message) VALUES ($1, $2)")
-            .bind(thread_id)
-            .bind(message),
-    )
-    .await
-    .map_err(actix_web::error::ErrorInternalServerError)?;
+        let thread: Option<(i32, bool, bool)> =
+            sqlx::query_as("SELECT board_id, locked, archived FROM threads WHERE id = $1")
+                .bind(thread_id)
+                .fetch_optional(pool.get_ref())
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+
+        let board = match thread {
+            None => {
+                return Err(refused(HttpResponse::NotFound()
+                    .content_type("text/html")
+                    .body(render_error_page("Not Found", "Thread not found."))));
+            }
+            Some((_, _, true)) => {
+                return Err(refused(HttpResponse::Forbidden()
+                    .content_type("text/html")
+                    .body(render_error_page(
+                        "Thread Archived",
+                        "This thread is archived and no longer accepts replies.",
+                    ))));
+            }
+            Some((_, true, _)) => {
+                return Err(refused(HttpResponse::Forbidden()
+                    .content_type("text/html")
+                    .body(render_error_page(
+                        "Thread Locked",
+                        "This thread is locked and no longer accepts replies.",
+                    ))));
+            }
+            Some((board_id, false, false)) => match registry.get(board_id) {
+                Some(board) => board,
+                None => return Err(refused(board_not_found())),
+            },
+        };
 
-    // Update the thread's last_updated timestamp
-    tx.execute(
-        sqlx::query("UPDATE threads SET last_updated = $1 WHERE id = $2")
-            .bind(now)
-            .bind(thread_id),
-    )
-    .await
-    .map_err(actix_web::error::ErrorInternalServerError)?;
+        let poster = identifier.identify(&req)?;
+        let ban = bans::find_active(pool.get_ref(), &poster, board.id)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        if let Some(ban) = ban {
+            return Err(refused(bans::banned_response(&ban, &registry)));
+        }
+
+        let now = Utc::now().timestamp();
+
+        let duplicate = ratelimit::is_duplicate(pool.get_ref(), &board, &poster.hash, message, now)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        if duplicate {
+            return Err(refused(ratelimit::duplicate_response()));
+        }
+
+        if let Some(saved) = &media {
+            let repost = hashes::find_repost(pool.get_ref(), &board, &saved.hash, now)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+            if let Some(post_no) = repost {
+                return Err(refused(hashes::repost_response(&board, post_no)));
+            }
+        }
+
+        // Only a post that passed every check uses up the poster's cooldown
+        if let Err(wait) = limiter.acquire(poster.ip, &board, PostKind::Reply) {
+            return Err(refused(ratelimit::rate_limited_response(PostKind::Reply, wait)));
+        }
+
+        // Begin a transaction
+        let mut tx: Transaction<'_, Postgres> = pool
+            .begin()
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+
+        if let Some(saved) = &media {
+            let present = media::claim_shared(&mut tx, saved)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+            if !present {
+                return Err(refused(hashes::shared_file_gone_response()));
+            }
+        }
+
+        let post_no = next_post_no(&mut tx, board.id)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    // Commit the transaction
-    tx.commit()
+        // Insert the reply
+        tx.execute(
+            sqlx::query(
+                "INSERT INTO replies (thread_id, message, name, tripcode, media_url, media_type, thumb_url, media_width, media_height, media_size, media_hash, media_phash, poster_hash, created_at, post_no) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)",
+            )
+            .bind(thread_id)
+            .bind(message)
+            .bind(&signature.name)
+            .bind(&signature.tripcode)
+            .bind(media.as_ref().map(|m| m.url.clone()))
+            .bind(media.as_ref().map(|m| m.media_type.clone()))
+            .bind(media.as_ref().and_then(|m| m.thumb_url.clone()))
+            .bind(media.as_ref().and_then(|m| m.width))
+            .bind(media.as_ref().and_then(|m| m.height))
+            .bind(media.as_ref().map(|m| m.size))
+            .bind(media.as_ref().map(|m| m.hash.clone()))
+            .bind(media.as_ref().and_then(|m| m.phash))
+            .bind(&poster.hash)
+            .bind(now)
+            .bind(post_no),
+        )
         .await
         .map_err(actix_web::error::ErrorInternalServerError)?;
 
+        // Update the thread's last_updated timestamp, unless the poster saged or the
+        // thread has passed the board's bump limit
+        if !sage {
+            tx.execute(
+                sqlx::query(
+                    r#"UPDATE threads SET last_updated = $1
+                    WHERE id = $2 AND (SELECT COUNT(*) FROM replies WHERE thread_id = $2) <= $3"#,
+                )
+                .bind(now)
+                .bind(thread_id)
+                .bind(i64::from(board.bump_limit)),
+            )
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        }
+
+        // Commit the transaction
+        tx.commit()
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        Ok((thread_id, post_no))
+    }
+    .await;
+    if result.is_err() {
+        upload::discard(media);
+    }
+    let (thread_id, post_no) = result?;
+
     Ok(HttpResponse::SeeOther()
-        .insert_header((LOCATION, format!("/thread/{}", thread_id)))
+        .insert_header((LOCATION, format!("/thread/{}#p{}", thread_id, post_no)))
         .finish())
 }
 
-// ADMIN: Delete Thread
-async fn admin_delete_thread_form(path: web::Path<(i32,)>) -> HttpResponse {
-    let thread_id = path.into_inner().0;
-    let action_url = format!("/admin/thread/delete/{}", thread_id);
-    let html = render_password_prompt(
-        &action_url,
-        "Delete Thread",
-        "Enter admin password to delete this thread:",
-    );
-    HttpResponse::Ok().content_type("text/html").body(html)
+// Takes the next post number on a board. The counter row stays locked until the
+// transaction ends, so numbers are handed out in order and never reused.
+async fn next_post_no(
+    tx: &mut Transaction<'_, Postgres>,
+    board_id: i32,
+) -> Result<i32, sqlx::Error> {
+    sqlx::query_scalar(
+        "UPDATE boards SET post_counter = post_counter + 1 WHERE id = $1 RETURNING post_counter",
+    )
+    .bind(board_id)
+    .fetch_one(&mut **tx)
+    .await
 }
 
-async fn admin_delete_thread_action(
-    pool: web::Data<Pool<Postgres>>,
-    path: web::Path<(i32,)>,
-    form: web::Form<AdminActionForm>,
-) -> Result<HttpResponse, Error> {
-    if !check_password(&form.password) {
-        return Ok(HttpResponse::Forbidden().body("Invalid password"));
-    }
+fn report_not_found() -> HttpResponse {
+    HttpResponse::NotFound()
+        .content_type("text/html")
+        .body(render_error_page("Not Found", "Post not found."))
+}
 
-    let thread_id = path.into_inner().0;
-    sqlx::query("DELETE FROM threads WHERE id = $1")
-        .bind(thread_id)
-        .execute(pool.get_ref())
+// Finds a reportable post; posts on deleted boards are treated as missing
+async fn locate_report_target(
+    pool: &Pool<Postgres>,
+    registry: &BoardRegistry,
+    kind: &str,
+    id: i32,
+) -> Result<Option<(ReportTarget, i32)>, Error> {
+    let target = match ReportTarget::parse(kind, id) {
+        Some(target) => target,
+        None => return Ok(None),
+    };
+    let location = target
+        .locate(pool)
         .await
         .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(location
+        .filter(|(_, board_id)| registry.get(*board_id).is_some())
+        .map(|(thread_id, _)| (target, thread_id)))
+}
 
-    // Optionally, delete associated replies if not using ON DELETE CASCADE
-    // sqlx::query("DELETE FROM replies WHERE thread_id = $1")
-    //     .bind(thread_id)
-    //     .execute(pool.get_ref())
-    //     .await
-    //     .map_err(actix_web::error::ErrorInternalServerError)?;
+// Report form for a thread or reply
+async fn report_form(
+    pool: web::Data<Pool<Postgres>>,
+    registry: web::Data<BoardRegistry>,
+    path: web::Path<(String, i32)>,
+    csrf: CsrfToken,
+) -> Result<HttpResponse, Error> {
+    let (kind, id) = path.into_inner();
+    let (target, thread_id) =
+        match locate_report_target(pool.get_ref(), &registry, &kind, id).await? {
+            Some(found) => found,
+            None => return Ok(report_not_found()),
+        };
 
-    Ok(HttpResponse::SeeOther()
-        .insert_header((LOCATION, "/"))
-        .finish())
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Report</title>
+    <link rel="stylesheet" href="/static/style.css">
+</head>
+<body>
+    <h1>Report {} {}</h1>
+    <form class="postform" action="/report/{}/{}" method="post">
+        {}
+        <input type="text" name="reason" maxlength="{}" placeholder="Reason (e.g. spam, off-topic, illegal content)" required>
+        <input type="submit" value="Report">
+    </form>
+    <p><a href="/thread/{}">[Return]</a></p>
+</body>
+</html>"#,
+        target.kind(),
+        target.id(),
+        target.kind(),
+        target.id(),
+        csrf.field(),
+        config::get().limits.report_reason_max_len,
+        thread_id
+    );
+    Ok(HttpResponse::Ok().content_type("text/html").body(html))
 }
 
-// ADMIN: Delete Reply
-async fn admin_delete_reply_form(path: web::Path<(i32,)>) -> HttpResponse {
-    let reply_id = path.into_inner().0;
-    let action_url = format!("/admin/reply/delete/{}", reply_id);
-    let html = render_password_prompt(
-        &action_url,
-        "Delete Reply",
-        "Enter admin password to delete this reply:",
-    );
-    HttpResponse::Ok().content_type("text/html").body(html)
+#[derive(Deserialize)]
+struct ReportForm {
+    reason: String,
 }
 
-async fn admin_delete_reply_action(
+async fn report_action(
+    req: HttpRequest,
     pool: web::Data<Pool<Postgres>>,
-    path: web::Path<(i32,)>,
-    form: web::Form<AdminActionForm>,
+    registry: web::Data<BoardRegistry>,
+    identifier: web::Data<PosterIdentifier>,
+    path: web::Path<(String, i32)>,
+    form: web::Form<ReportForm>,
 ) -> Result<HttpResponse, Error> {
-    if !check_password(&form.password) {
-        return Ok(HttpResponse::Forbidden().body("Invalid password"));
-    }
-
-    let reply_id = path.into_inner().0;
+    let (kind, id) = path.into_inner();
+    let (target, thread_id) =
+        match locate_report_target(pool.get_ref(), &registry, &kind, id).await? {
+            Some(found) => found,
+            None => return Ok(report_not_found()),
+        };
 
-    // Need thread_id to redirect back to thread after deletion
-    let thread_id: Option<i32> = sqlx::query_scalar("SELECT thread_id FROM replies WHERE id = $1")
-        .bind(reply_id)
-        .fetch_optional(pool.get_ref())
-        .await
-        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let max_len = config::get().limits.report_reason_max_len;
+    let reason = form.reason.trim();
+    if reason.is_empty() || reason.chars().count() > max_len {
+        return Ok(HttpResponse::BadRequest()
+            .content_type("text/html")
+            .body(render_error_page(
+                "Bad Request",
+                &format!("Give a reason of at most {} characters.", max_len),
+            )));
+    }
 
-    sqlx::query("DELETE FROM replies WHERE id = $1")
-        .bind(reply_id)
-        .execute(pool.get_ref())
-        .await
-        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let reporter = identifier.identify(&req)?;
+    let filed = reports::file(
+        pool.get_ref(),
+        target,
+        reason,
+        &reporter.hash,
+        Utc::now().timestamp(),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    let redirect_url = if let Some(tid) = thread_id {
-        format!("/thread/{}", tid)
+    let message = if filed {
+        "Thank you. The report has been sent to the moderators."
     } else {
-        "/".to_string()
+        "You have already reported this post."
     };
-
-    Ok(HttpResponse::SeeOther()
-        .insert_header((LOCATION, redirect_url))
-        .finish())
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Report</title>
+    <link rel="stylesheet" href="/static/style.css">
+</head>
+<body>
+    <h1>Report Received</h1>
+    <p>{}</p>
+    <p><a href="/thread/{}">[Return]</a></p>
+</body>
+</html>"#,
+        message, thread_id
+    );
+    Ok(HttpResponse::Ok().content_type("text/html").body(html))
 }
 
-// ADMIN: Delete Board
-async fn admin_delete_board_form(path: web::Path<(i32,)>) -> HttpResponse {
-    let board_id = path.into_inner().0;
-    let action_url = format!("/admin/boards/delete/{}", board_id);
-    let html = render_password_prompt(
-        &action_url,
-        "Delete Board",
-        "Enter admin password to delete this board:",
-    );
+// ADMIN: Login
+async fn admin_login_form(query: web::Query<NextQuery>, csrf: CsrfToken) -> HttpResponse {
+    let action_url = auth::login_url(&auth::safe_next(query.next.as_deref()));
+    let html = render_password_prompt(&action_url, "Admin Login", "Log in to continue:", &csrf);
     HttpResponse::Ok().content_type("text/html").body(html)
 }
 
-async fn admin_delete_board_action(
+async fn admin_login_action(
+    req: HttpRequest,
     pool: web::Data<Pool<Postgres>>,
-    path: web::Path<(i32,)>,
-    form: web::Form<AdminActionForm>,
+    session: Session,
+    identifier: web::Data<PosterIdentifier>,
+    limiter: web::Data<RateLimiter>,
+    query: web::Query<NextQuery>,
+    form: web::Form<LoginForm>,
 ) -> Result<HttpResponse, Error> {
-    if !check_password(&form.password) {
-        return Ok(HttpResponse::Forbidden().body("Invalid password"));
+    let ip = identifier.identify(&req)?.ip;
+    if let Err(wait) = limiter.acquire_login(ip) {
+        return Ok(ratelimit::login_limited_response(wait));
     }
 
-    let board_id = path.into_inner().0;
-    sqlx::query("UPDATE boards SET deleted = TRUE WHERE id = $1")
-        .bind(board_id)
-        .execute(pool.get_ref())
+    let username = form.username.trim();
+    let valid = auth::authenticate(pool.get_ref(), username, &form.password)
         .await
         .map_err(actix_web::error::ErrorInternalServerError)?;
 
+    if !valid {
+        return Ok(HttpResponse::Forbidden()
+            .content_type("text/html")
+            .body(render_error_page("Forbidden", "Invalid username or password.")));
+    }
+
+    limiter.login_succeeded(ip);
+    auth::login(&session, username).map_err(actix_web::error::ErrorInternalServerError)?;
+
     Ok(HttpResponse::SeeOther()
-        .insert_header((LOCATION, "/"))
+        .insert_header((LOCATION, auth::safe_next(query.next.as_deref())))
         .finish())
 }
 
-// ADMIN: Edit Board
-#[derive(Deserialize)]
-struct EditBoardData {
-    password: String,
-    name: String,
+// ADMIN: Logout
+async fn admin_logout(session: Session) -> HttpResponse {
+    auth::logout(&session);
+    HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/"))
+        .finish()
 }
 
-async fn admin_edit_board_form(path: web::Path<(i32,)>) -> HttpResponse {
-    let board_id = path.into_inner().0;
-    let action_url = format!("/admin/boards/edit/{}", board_id);
+// ADMIN: Dashboard
+async fn admin_index(
+    admin: AdminUser,
+    csrf: CsrfToken,
+    pool: web::Data<Pool<Postgres>>,
+) -> Result<HttpResponse, Error> {
+    let open_reports = reports::open_count(pool.get_ref())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
     let html = format!(
         r#"<!DOCTYPE html>
-<html>
+<html lang="en">
 <head>
     <meta charset="UTF-8">
-    <title>Edit Board</title>
+    <title>Admin</title>
     <link rel="stylesheet" href="/static/style.css">
 </head>
 <body>
-    <h1>Edit Board {}</h1>
-    <form action="{}" method="post">
-        <p>Enter admin password and new board name:</p>
-        <input type="password" name="password" placeholder="Admin Password" required><br>
-        <input type="text" name="name" placeholder="New Board Name" required>
-        <input type="submit" value="Update">
+    <h1>Admin</h1>
+    <p>Logged in as {}.</p>
+    <p><a href="/admin/reports">[Reports ({})]</a> <a href="/admin/boards">[Boards]</a> <a href="/admin/bans">[Bans]</a> <a href="/admin/files">[Banned Files and Images]</a></p>
+    <form action="/admin/logout" method="post">
+        {}
+        <input type="submit" value="Log Out">
     </form>
     <p><a href="/">[Home]</a></p>
 </body>
 </html>"#,
-        board_id, action_url
+        escape_html(&admin.username),
+        open_reports,
+        csrf.field()
     );
-    HttpResponse::Ok().content_type("text/html").body(html)
+    Ok(HttpResponse::Ok().content_type("text/html").body(html))
 }
 
-async fn admin_edit_board_action(
-    pool: web::Data<Pool<Postgres>>,
+// ADMIN: Delete Thread
+async fn admin_delete_thread_form(
+    _admin: AdminUser,
+    csrf: CsrfToken,
     path: web::Path<(i32,)>,
-    form: web::Form<EditBoardData>,
-) -> Result<HttpResponse, Error> {
-    if !check_password(&form.password) {
-        return Ok(HttpResponse::Forbidden().body("Invalid password"));
-    }
+    query: web::Query<NextQuery>,
+) -> HttpResponse {
+    let thread_id = path.into_inner().0;
+    let action_url = format!(
+        "/admin/thread/delete/{}{}",
+        thread_id,
+        next_query(query.next.as_deref())
+    );
+    let html = render_confirm_prompt(
+        &action_url,
+        "Delete Thread",
+        "Delete this thread and all of its replies?",
+        &csrf,
+    );
+    HttpResponse::Ok().content_type("text/html").body(html)
+}
+
+async fn admin_delete_thread_action(
+    _admin: AdminUser,
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<(i32,)>,
+    query: web::Query<NextQuery>,
+) -> Result<HttpResponse, Error> {
+    let thread_id = path.into_inner().0;
+
+    // Replies go with the thread (ON DELETE CASCADE); media files are removed too
+    media::delete_thread(pool.get_ref(), thread_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let redirect_url = match query.next.as_deref() {
+        Some(next) => auth::safe_next(Some(next)),
+        None => "/".to_string(),
+    };
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, redirect_url))
+        .finish())
+}
+
+// ADMIN: Pin / Unpin / Lock / Unlock Thread
+enum ThreadFlagAction {
+    Pin,
+    Unpin,
+    Lock,
+    Unlock,
+}
+
+impl ThreadFlagAction {
+    fn parse(action: &str) -> Option<Self> {
+        match action {
+            "pin" => Some(Self::Pin),
+            "unpin" => Some(Self::Unpin),
+            "lock" => Some(Self::Lock),
+            "unlock" => Some(Self::Unlock),
+            _ => None,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Pin => "Pin",
+            Self::Unpin => "Unpin",
+            Self::Lock => "Lock",
+            Self::Unlock => "Unlock",
+        }
+    }
+
+    // The column name is chosen from a fixed set, never from user input
+    fn update_query(&self) -> &'static str {
+        match self {
+            Self::Pin => "UPDATE threads SET pinned = TRUE WHERE id = $1",
+            Self::Unpin => "UPDATE threads SET pinned = FALSE WHERE id = $1",
+            Self::Lock => "UPDATE threads SET locked = TRUE WHERE id = $1",
+            Self::Unlock => "UPDATE threads SET locked = FALSE WHERE id = $1",
+        }
+    }
+}
+
+async fn admin_thread_flag_form(
+    _admin: AdminUser,
+    csrf: CsrfToken,
+    path: web::Path<(String, i32)>,
+) -> HttpResponse {
+    let (action, thread_id) = path.into_inner();
+    let flag = match ThreadFlagAction::parse(&action) {
+        Some(flag) => flag,
+        None => {
+            return HttpResponse::NotFound()
+                .content_type("text/html")
+                .body(render_error_page("Not Found", "Unknown thread action."));
+        }
+    };
+
+    let action_url = format!("/admin/thread/{}/{}", action, thread_id);
+    let title = format!("{} Thread", flag.label());
+    let prompt = format!("{} thread {}?", flag.label(), thread_id);
+    let html = render_confirm_prompt(&action_url, &title, &prompt, &csrf);
+    HttpResponse::Ok().content_type("text/html").body(html)
+}
+
+async fn admin_thread_flag_action(
+    _admin: AdminUser,
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<(String, i32)>,
+) -> Result<HttpResponse, Error> {
+    let (action, thread_id) = path.into_inner();
+    let flag = match ThreadFlagAction::parse(&action) {
+        Some(flag) => flag,
+        None => {
+            return Ok(HttpResponse::NotFound()
+                .content_type("text/html")
+                .body(render_error_page("Not Found", "Unknown thread action.")));
+        }
+    };
+
+    sqlx::query(flag.update_query())
+        .bind(thread_id)
+        .execute(pool.get_ref())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, format!("/thread/{}", thread_id)))
+        .finish())
+}
+
+// ADMIN: Delete Reply
+async fn admin_delete_reply_form(
+    _admin: AdminUser,
+    csrf: CsrfToken,
+    path: web::Path<(i32,)>,
+    query: web::Query<NextQuery>,
+) -> HttpResponse {
+    let reply_id = path.into_inner().0;
+    let action_url = format!(
+        "/admin/reply/delete/{}{}",
+        reply_id,
+        next_query(query.next.as_deref())
+    );
+    let html = render_confirm_prompt(&action_url, "Delete Reply", "Delete this reply?", &csrf);
+    HttpResponse::Ok().content_type("text/html").body(html)
+}
+
+async fn admin_delete_reply_action(
+    _admin: AdminUser,
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<(i32,)>,
+    query: web::Query<NextQuery>,
+) -> Result<HttpResponse, Error> {
+    let reply_id = path.into_inner().0;
+
+    // Need thread_id to redirect back to thread after deletion
+    let thread_id = media::delete_reply(pool.get_ref(), reply_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let redirect_url = if let Some(next) = query.next.as_deref() {
+        auth::safe_next(Some(next))
+    } else if let Some(tid) = thread_id {
+        format!("/thread/{}", tid)
+    } else {
+        "/".to_string()
+    };
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, redirect_url))
+        .finish())
+}
+
+// ADMIN: Board list
+async fn admin_boards(
+    _admin: AdminUser,
+    csrf: CsrfToken,
+    registry: web::Data<BoardRegistry>,
+) -> HttpResponse {
+    let rows = registry
+        .all()
+        .into_iter()
+        .map(|board| {
+            let (status, toggle) = if board.deleted {
+                (
+                    "Deleted",
+                    format!(
+                        r#"<a href="/admin/boards/restore/{id}">[restore]</a> <a href="/admin/boards/purge/{id}">[purge]</a>"#,
+                        id = board.id
+                    ),
+                )
+            } else {
+                (
+                    "Live",
+                    format!(r#"<a href="/admin/boards/delete/{}">[delete]</a>"#, board.id),
+                )
+            };
+            format!(
+                r#"<tr><td>{}</td><td>/{}/</td><td>{}</td><td>{}</td><td>{}</td><td><a href="/admin/boards/edit/{}">[edit]</a> {}</td></tr>"#,
+                board.id,
+                escape_html(&board.uri),
+                escape_html(&board.name),
+                escape_html(&board.description),
+                status,
+                board.id,
+                toggle
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let defaults = &config::get().board_defaults;
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Boards</title>
+    <link rel="stylesheet" href="/static/style.css">
+</head>
+<body>
+    <h1>Boards</h1>
+    <table class="admin-table">
+        <tr><th>ID</th><th>URI</th><th>Name</th><th>Description</th><th>Status</th><th></th></tr>
+        {}
+    </table>
+    <h2>Create Board</h2>
+    <form class="postform" action="/admin/boards/create" method="post">
+        {}
+        <input type="text" name="uri" maxlength="16" placeholder="URI (e.g. b)" required>
+        <input type="text" name="name" placeholder="Board Name" required>
+        <textarea name="description" rows="2" placeholder="Description"></textarea>
+        <label>Bump limit (replies that still bump a thread):</label>
+        <input type="number" name="bump_limit" min="1" value="{}" required>
+        <label>Maximum live threads:</label>
+        <input type="number" name="max_threads" min="1" value="{}" required>
+        <label><input type="checkbox" name="archive_pruned"{}> Archive pruned threads instead of deleting them</label>
+        <label>Seconds between new threads from one poster:</label>
+        <input type="number" name="thread_cooldown" min="0" value="{}" required>
+        <label>Seconds between replies from one poster:</label>
+        <input type="number" name="reply_cooldown" min="0" value="{}" required>
+        <label>Days before the same file can be posted again (0 allows reposts):</label>
+        <input type="number" name="repost_days" min="0" value="{}" required>
+        <input type="submit" value="Create">
+    </form>
+    <p><a href="/admin">[Admin]</a> <a href="/">[Home]</a></p>
+</body>
+</html>"#,
+        rows,
+        csrf.field(),
+        defaults.bump_limit,
+        defaults.max_threads,
+        if defaults.archive_pruned { " checked" } else { "" },
+        defaults.thread_cooldown,
+        defaults.reply_cooldown,
+        defaults.repost_days
+    );
+    HttpResponse::Ok().content_type("text/html").body(html)
+}
+
+#[derive(Deserialize)]
+struct BoardForm {
+    uri: String,
+    name: String,
+    #[serde(default)]
+    description: String,
+    bump_limit: i32,
+    max_threads: i32,
+    // Checkbox: present only when ticked
+    archive_pruned: Option<String>,
+    thread_cooldown: i32,
+    reply_cooldown: i32,
+    repost_days: i32,
+}
+
+// Board fields after trimming and validation
+struct BoardSettings {
+    uri: String,
+    name: String,
+    description: String,
+    bump_limit: i32,
+    max_threads: i32,
+    archive_pruned: bool,
+    thread_cooldown: i32,
+    reply_cooldown: i32,
+    repost_days: i32,
+}
+
+impl BoardForm {
+    // Returns the cleaned-up settings or a message explaining what is wrong
+    fn validate(&self) -> Result<BoardSettings, &'static str> {
+        let uri = self.uri.trim().to_lowercase();
+        let name = self.name.trim().to_string();
+        let description = self.description.trim().to_string();
+
+        if !board::is_valid_uri(&uri) {
+            return Err("Board URI must be 1-16 lowercase letters or digits and not a reserved name.");
+        }
+        if name.is_empty() {
+            return Err("Board name cannot be empty.");
+        }
+        if self.bump_limit < 1 || self.max_threads < 1 {
+            return Err("Bump limit and maximum threads must be at least 1.");
+        }
+        if self.thread_cooldown < 0 || self.reply_cooldown < 0 {
+            return Err("Cooldowns cannot be negative.");
+        }
+        if self.repost_days < 0 {
+            return Err("Repost days cannot be negative.");
+        }
+        Ok(BoardSettings {
+            uri,
+            name,
+            description,
+            bump_limit: self.bump_limit,
+            max_threads: self.max_threads,
+            archive_pruned: self.archive_pruned.is_some(),
+            thread_cooldown: self.thread_cooldown,
+            reply_cooldown: self.reply_cooldown,
+            repost_days: self.repost_days,
+        })
+    }
+}
+
+fn is_unique_violation(err: &sqlx::Error) -> bool {
+    matches!(err, sqlx::Error::Database(db_err) if db_err.code().as_deref() == Some("23505"))
+}
+
+fn admin_form_error(message: &str) -> HttpResponse {
+    HttpResponse::BadRequest()
+        .content_type("text/html")
+        .body(render_error_page("Bad Request", message))
+}
+
+// ADMIN: Create Board
+async fn admin_create_board_action(
+    _admin: AdminUser,
+    pool: web::Data<Pool<Postgres>>,
+    registry: web::Data<BoardRegistry>,
+    form: web::Form<BoardForm>,
+) -> Result<HttpResponse, Error> {
+    let settings = match form.validate() {
+        Ok(settings) => settings,
+        Err(message) => return Ok(admin_form_error(message)),
+    };
+
+    let result = sqlx::query(
+        r#"INSERT INTO boards (uri, name, description, bump_limit, max_threads, archive_pruned,
+            thread_cooldown, reply_cooldown, repost_days)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)"#,
+    )
+    .bind(&settings.uri)
+    .bind(&settings.name)
+    .bind(&settings.description)
+    .bind(settings.bump_limit)
+    .bind(settings.max_threads)
+    .bind(settings.archive_pruned)
+    .bind(settings.thread_cooldown)
+    .bind(settings.reply_cooldown)
+    .bind(settings.repost_days)
+    .execute(pool.get_ref())
+    .await;
+
+    match result {
+        Ok(_) => {}
+        Err(e) if is_unique_violation(&e) => {
+            return Ok(admin_form_error("That board URI is already in use."));
+        }
+        Err(e) => return Err(actix_web::error::ErrorInternalServerError(e)),
+    }
+
+    registry
+        .reload(pool.get_ref())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/admin/boards"))
+        .finish())
+}
+
+// ADMIN: Delete Board
+async fn admin_delete_board_form(
+    _admin: AdminUser,
+    csrf: CsrfToken,
+    path: web::Path<(i32,)>,
+) -> HttpResponse {
+    let board_id = path.into_inner().0;
+    let action_url = format!("/admin/boards/delete/{}", board_id);
+    let html = render_confirm_prompt(
+        &action_url,
+        "Delete Board",
+        "Delete this board? It can be restored later.",
+        &csrf,
+    );
+    HttpResponse::Ok().content_type("text/html").body(html)
+}
+
+async fn admin_delete_board_action(
+    _admin: AdminUser,
+    pool: web::Data<Pool<Postgres>>,
+    registry: web::Data<BoardRegistry>,
+    path: web::Path<(i32,)>,
+) -> Result<HttpResponse, Error> {
     let board_id = path.into_inner().0;
-    sqlx::query("UPDATE boards SET name = $1 WHERE id = $2")
-        .bind(&form.name)
+    sqlx::query("UPDATE boards SET deleted = TRUE WHERE id = $1")
         .bind(board_id)
         .execute(pool.get_ref())
         .await
         .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    registry
+        .reload(pool.get_ref())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
     Ok(HttpResponse::SeeOther()
-        .insert_header((LOCATION, "/"))
+        .insert_header((LOCATION, "/admin/boards"))
+        .finish())
+}
+
+// ADMIN: Restore Board
+async fn admin_restore_board_form(
+    _admin: AdminUser,
+    csrf: CsrfToken,
+    path: web::Path<(i32,)>,
+) -> HttpResponse {
+    let board_id = path.into_inner().0;
+    let action_url = format!("/admin/boards/restore/{}", board_id);
+    let html =
+        render_confirm_prompt(&action_url, "Restore Board", "Restore this board?", &csrf);
+    HttpResponse::Ok().content_type("text/html").body(html)
+}
+
+async fn admin_restore_board_action(
+    _admin: AdminUser,
+    pool: web::Data<Pool<Postgres>>,
+    registry: web::Data<BoardRegistry>,
+    path: web::Path<(i32,)>,
+) -> Result<HttpResponse, Error> {
+    let board_id = path.into_inner().0;
+    sqlx::query("UPDATE boards SET deleted = FALSE WHERE id = $1")
+        .bind(board_id)
+        .execute(pool.get_ref())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    registry
+        .reload(pool.get_ref())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/admin/boards"))
+        .finish())
+}
+
+// ADMIN: Purge Board (permanently removes the threads and media of a deleted board)
+async fn admin_purge_board_form(
+    _admin: AdminUser,
+    csrf: CsrfToken,
+    path: web::Path<(i32,)>,
+) -> HttpResponse {
+    let board_id = path.into_inner().0;
+    let action_url = format!("/admin/boards/purge/{}", board_id);
+    let html = render_confirm_prompt(
+        &action_url,
+        "Purge Board",
+        "Permanently delete every thread and uploaded file on this board? This cannot be undone.",
+        &csrf,
+    );
+    HttpResponse::Ok().content_type("text/html").body(html)
+}
+
+async fn admin_purge_board_action(
+    _admin: AdminUser,
+    pool: web::Data<Pool<Postgres>>,
+    registry: web::Data<BoardRegistry>,
+    path: web::Path<(i32,)>,
+) -> Result<HttpResponse, Error> {
+    let board_id = path.into_inner().0;
+
+    // Only soft-deleted boards can be purged, so a live board is never emptied by accident
+    if registry.get(board_id).is_some() {
+        return Ok(admin_form_error("Delete the board before purging it."));
+    }
+
+    media::purge_board(pool.get_ref(), board_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/admin/boards"))
+        .finish())
+}
+
+// ADMIN: Edit Board
+async fn admin_edit_board_form(
+    _admin: AdminUser,
+    csrf: CsrfToken,
+    registry: web::Data<BoardRegistry>,
+    path: web::Path<(i32,)>,
+) -> HttpResponse {
+    let board_id = path.into_inner().0;
+    let board = match registry.all().into_iter().find(|b| b.id == board_id) {
+        Some(board) => board,
+        None => return board_not_found(),
+    };
+
+    let action_url = format!("/admin/boards/edit/{}", board_id);
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <title>Edit Board</title>
+    <link rel="stylesheet" href="/static/style.css">
+</head>
+<body>
+    <h1>Edit Board {}</h1>
+    <form class="postform" action="{}" method="post">
+        {}
+        <input type="text" name="uri" maxlength="16" value="{}" placeholder="URI" required>
+        <input type="text" name="name" value="{}" placeholder="Board Name" required>
+        <textarea name="description" rows="2" placeholder="Description">{}</textarea>
+        <label>Bump limit (replies that still bump a thread):</label>
+        <input type="number" name="bump_limit" min="1" value="{}" required>
+        <label>Maximum live threads:</label>
+        <input type="number" name="max_threads" min="1" value="{}" required>
+        <label><input type="checkbox" name="archive_pruned"{}> Archive pruned threads instead of deleting them</label>
+        <label>Seconds between new threads from one poster:</label>
+        <input type="number" name="thread_cooldown" min="0" value="{}" required>
+        <label>Seconds between replies from one poster:</label>
+        <input type="number" name="reply_cooldown" min="0" value="{}" required>
+        <label>Days before the same file can be posted again (0 allows reposts):</label>
+        <input type="number" name="repost_days" min="0" value="{}" required>
+        <input type="submit" value="Update">
+    </form>
+    <p><a href="/admin/boards">[Boards]</a> <a href="/">[Home]</a></p>
+</body>
+</html>"#,
+        board_id,
+        action_url,
+        csrf.field(),
+        escape_html(&board.uri),
+        escape_html(&board.name),
+        escape_html(&board.description),
+        board.bump_limit,
+        board.max_threads,
+        if board.archive_pruned { " checked" } else { "" },
+        board.thread_cooldown,
+        board.reply_cooldown,
+        board.repost_days
+    );
+    HttpResponse::Ok().content_type("text/html").body(html)
+}
+
+async fn admin_edit_board_action(
+    _admin: AdminUser,
+    pool: web::Data<Pool<Postgres>>,
+    registry: web::Data<BoardRegistry>,
+    path: web::Path<(i32,)>,
+    form: web::Form<BoardForm>,
+) -> Result<HttpResponse, Error> {
+    let board_id = path.into_inner().0;
+    let settings = match form.validate() {
+        Ok(settings) => settings,
+        Err(message) => return Ok(admin_form_error(message)),
+    };
+
+    let result = sqlx::query(
+        r#"UPDATE boards SET uri = $1, name = $2, description = $3,
+            bump_limit = $4, max_threads = $5, archive_pruned = $6,
+            thread_cooldown = $7, reply_cooldown = $8, repost_days = $9
+        WHERE id = $10"#,
+    )
+    .bind(&settings.uri)
+    .bind(&settings.name)
+    .bind(&settings.description)
+    .bind(settings.bump_limit)
+    .bind(settings.max_threads)
+    .bind(settings.archive_pruned)
+    .bind(settings.thread_cooldown)
+    .bind(settings.reply_cooldown)
+    .bind(settings.repost_days)
+    .bind(board_id)
+    .execute(pool.get_ref())
+    .await;
+
+    match result {
+        Ok(_) => {}
+        Err(e) if is_unique_violation(&e) => {
+            return Ok(admin_form_error("That board URI is already in use."));
+        }
+        Err(e) => return Err(actix_web::error::ErrorInternalServerError(e)),
+    }
+
+    registry
+        .reload(pool.get_ref())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/admin/boards"))
+        .finish())
+}
+
+#[derive(Deserialize)]
+struct BanQuery {
+    thread: Option<i32>,
+    reply: Option<i32>,
+}
+
+// ADMIN: Ban list, with a form prefilled from the post when opened from its [ban] link
+async fn admin_bans(
+    _admin: AdminUser,
+    csrf: CsrfToken,
+    pool: web::Data<Pool<Postgres>>,
+    registry: web::Data<BoardRegistry>,
+    query: web::Query<BanQuery>,
+) -> Result<HttpResponse, Error> {
+    let post: Option<(Option<String>, i32)> = if let Some(thread_id) = query.thread {
+        sqlx::query_as("SELECT poster_hash, board_id FROM threads WHERE id = $1")
+            .bind(thread_id)
+            .fetch_optional(pool.get_ref())
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?
+    } else if let Some(reply_id) = query.reply {
+        sqlx::query_as(
+            r#"SELECT r.poster_hash, t.board_id FROM replies r
+            JOIN threads t ON t.id = r.thread_id WHERE r.id = $1"#,
+        )
+        .bind(reply_id)
+        .fetch_optional(pool.get_ref())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+    } else {
+        None
+    };
+    let (prefill_hash, prefill_board) = match post {
+        Some((hash, board_id)) => (hash.unwrap_or_default(), Some(board_id)),
+        None => (String::new(), None),
+    };
+
+    let bans = bans::list_active(pool.get_ref())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let rows = bans
+        .iter()
+        .map(|ban| {
+            let target = match (&ban.ip_range, &ban.poster_hash) {
+                (Some(range), _) => escape_html(range),
+                (None, Some(hash)) => format!("poster {}", escape_html(poster::short_hash(hash))),
+                (None, None) => String::new(),
+            };
+            format!(
+                r#"<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td><a href="/admin/bans/lift/{}">[lift]</a></td></tr>"#,
+                ban.id,
+                target,
+                escape_html(&bans::scope_label(ban, &registry)),
+                escape_html(&ban.reason),
+                format_timestamp(ban.created_at),
+                bans::expiry_label(ban),
+                escape_html(&ban.created_by),
+                ban.id
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let board_options = registry
+        .live()
+        .into_iter()
+        .map(|board| {
+            format!(
+                r#"<option value="{}"{}>/{}/ - {}</option>"#,
+                board.id,
+                if prefill_board == Some(board.id) { " selected" } else { "" },
+                escape_html(&board.uri),
+                escape_html(&board.name)
+            )
+        })
+        .collect::<String>();
+
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Bans</title>
+    <link rel="stylesheet" href="/static/style.css">
+</head>
+<body>
+    <h1>Active Bans</h1>
+    <table class="admin-table">
+        <tr><th>ID</th><th>Target</th><th>Scope</th><th>Reason</th><th>Created</th><th>Expires</th><th>By</th><th></th></tr>
+        {}
+    </table>
+    <h2>Add Ban</h2>
+    <form class="postform" action="/admin/bans/create" method="post">
+        {}
+        <label>IP address or CIDR range (e.g. 203.0.113.7 or 203.0.113.0/24):</label>
+        <input type="text" name="ip_range" placeholder="IP or range">
+        <label>Poster hash (filled in when banning from a post):</label>
+        <input type="text" name="poster_hash" value="{}" placeholder="Poster hash">
+        <label>Board:</label>
+        <select name="board_id"><option value="">All boards</option>{}</select>
+        <input type="text" name="reason" placeholder="Reason">
+        <label>Duration in hours (leave empty for a permanent ban):</label>
+        <input type="number" name="duration_hours" min="1">
+        <input type="submit" value="Ban">
+    </form>
+    <p><a href="/admin">[Admin]</a> <a href="/">[Home]</a></p>
+</body>
+</html>"#,
+        rows,
+        csrf.field(),
+        escape_html(&prefill_hash),
+        board_options
+    );
+    Ok(HttpResponse::Ok().content_type("text/html").body(html))
+}
+
+#[derive(Deserialize)]
+struct BanForm {
+    #[serde(default)]
+    ip_range: String,
+    #[serde(default)]
+    poster_hash: String,
+    // Empty for a ban on every board
+    #[serde(default)]
+    board_id: String,
+    #[serde(default)]
+    reason: String,
+    // Empty for a permanent ban
+    #[serde(default)]
+    duration_hours: String,
+}
+
+// ADMIN: Create Ban
+async fn admin_create_ban_action(
+    admin: AdminUser,
+    pool: web::Data<Pool<Postgres>>,
+    registry: web::Data<BoardRegistry>,
+    form: web::Form<BanForm>,
+) -> Result<HttpResponse, Error> {
+    let ip_range = match form.ip_range.trim() {
+        "" => None,
+        input => match bans::parse_ip_range(input) {
+            Some(range) => Some(range),
+            None => return Ok(admin_form_error("Not a valid IP address or CIDR range.")),
+        },
+    };
+    let poster_hash = Some(form.poster_hash.trim()).filter(|hash| !hash.is_empty());
+    if ip_range.is_none() && poster_hash.is_none() {
+        return Ok(admin_form_error("Enter an IP range or a poster hash to ban."));
+    }
+
+    let board_id = match form.board_id.trim() {
+        "" => None,
+        input => match input.parse::<i32>().ok().and_then(|id| registry.get(id)) {
+            Some(board) => Some(board.id),
+            None => return Ok(admin_form_error("Unknown board.")),
+        },
+    };
+
+    let now = Utc::now().timestamp();
+    let expires_at = match form.duration_hours.trim() {
+        "" => None,
+        input => match input.parse::<i64>() {
+            Ok(hours) if hours > 0 => Some(now + hours.saturating_mul(3600)),
+            _ => return Ok(admin_form_error("Duration must be a positive number of hours.")),
+        },
+    };
+
+    sqlx::query(
+        r#"INSERT INTO bans (ip_range, poster_hash, board_id, reason, created_by, created_at, expires_at)
+        VALUES (NETWORK($1::INET), $2, $3, $4, $5, $6, $7)"#,
+    )
+    .bind(ip_range)
+    .bind(poster_hash)
+    .bind(board_id)
+    .bind(form.reason.trim())
+    .bind(&admin.username)
+    .bind(now)
+    .bind(expires_at)
+    .execute(pool.get_ref())
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/admin/bans"))
+        .finish())
+}
+
+// ADMIN: Lift Ban
+async fn admin_lift_ban_form(
+    _admin: AdminUser,
+    csrf: CsrfToken,
+    path: web::Path<(i32,)>,
+) -> HttpResponse {
+    let ban_id = path.into_inner().0;
+    let action_url = format!("/admin/bans/lift/{}", ban_id);
+    let prompt = format!("Lift ban {}?", ban_id);
+    let html = render_confirm_prompt(&action_url, "Lift Ban", &prompt, &csrf);
+    HttpResponse::Ok().content_type("text/html").body(html)
+}
+
+async fn admin_lift_ban_action(
+    _admin: AdminUser,
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<(i32,)>,
+) -> Result<HttpResponse, Error> {
+    let ban_id = path.into_inner().0;
+
+    sqlx::query("DELETE FROM bans WHERE id = $1")
+        .bind(ban_id)
+        .execute(pool.get_ref())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/admin/bans"))
+        .finish())
+}
+
+// ADMIN: Banned file list, with the form prefilled from the post when opened from
+// its [ban file] link
+async fn admin_banned_files(
+    _admin: AdminUser,
+    csrf: CsrfToken,
+    pool: web::Data<Pool<Postgres>>,
+    query: web::Query<BanQuery>,
+) -> Result<HttpResponse, Error> {
+    let prefill_hash: Option<String> = if let Some(thread_id) = query.thread {
+        sqlx::query_scalar("SELECT media_hash FROM threads WHERE id = $1")
+            .bind(thread_id)
+            .fetch_optional(pool.get_ref())
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?
+            .flatten()
+    } else if let Some(reply_id) = query.reply {
+        sqlx::query_scalar("SELECT media_hash FROM replies WHERE id = $1")
+            .bind(reply_id)
+            .fetch_optional(pool.get_ref())
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?
+            .flatten()
+    } else {
+        None
+    };
+
+    let banned = hashes::list_banned(pool.get_ref())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let banned_images = hashes::list_banned_images(pool.get_ref())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let rows = banned
+        .iter()
+        .map(|file| {
+            format!(
+                r#"<tr><td>{}</td><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td><td><a href="/admin/files/lift/{}">[lift]</a></td></tr>"#,
+                file.id,
+                escape_html(&file.sha256),
+                escape_html(&file.reason),
+                format_timestamp(file.created_at),
+                escape_html(&file.created_by),
+                file.id
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let image_rows = banned_images
+        .iter()
+        .map(|image| {
+            format!(
+                r#"<tr><td>{}</td><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td><td><a href="/admin/images/lift/{}">[lift]</a></td></tr>"#,
+                image.id,
+                hashes::format_phash(image.phash),
+                escape_html(&image.reason),
+                format_timestamp(image.created_at),
+                escape_html(&image.created_by),
+                image.id
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Banned Files</title>
+    <link rel="stylesheet" href="/static/style.css">
+</head>
+<body>
+    <h1>Banned Files</h1>
+    <p>Uploads whose SHA-256 matches one of these are refused on every board.</p>
+    <table class="admin-table">
+        <tr><th>ID</th><th>SHA-256</th><th>Reason</th><th>Created</th><th>By</th><th></th></tr>
+        {}
+    </table>
+    <h2>Ban File</h2>
+    <form class="postform" action="/admin/files/create" method="post">
+        {}
+        <label>SHA-256 of the file (filled in when banning from a post):</label>
+        <input type="text" name="sha256" value="{}" maxlength="64" placeholder="SHA-256" required>
+        <input type="text" name="reason" placeholder="Reason">
+        <input type="submit" value="Ban">
+    </form>
+    <h2>Banned Images</h2>
+    <p>Images that look like one of these (perceptual hashes at most {} bits apart) are refused on every board. Add one with the [ban image] link on a post.</p>
+    <table class="admin-table">
+        <tr><th>ID</th><th>Perceptual hash</th><th>Reason</th><th>Created</th><th>By</th><th></th></tr>
+        {}
+    </table>
+    <p><a href="/admin">[Admin]</a> <a href="/">[Home]</a></p>
+</body>
+</html>"#,
+        rows,
+        csrf.field(),
+        escape_html(prefill_hash.as_deref().unwrap_or("")),
+        config::get().images.phash_max_distance,
+        image_rows,
+    );
+    Ok(HttpResponse::Ok().content_type("text/html").body(html))
+}
+
+#[derive(Deserialize)]
+struct BanFileForm {
+    sha256: String,
+    #[serde(default)]
+    reason: String,
+}
+
+// ADMIN: Add a file hash to the blocklist
+async fn admin_ban_file_action(
+    admin: AdminUser,
+    pool: web::Data<Pool<Postgres>>,
+    form: web::Form<BanFileForm>,
+) -> Result<HttpResponse, Error> {
+    let sha256 = match hashes::parse_sha256(&form.sha256) {
+        Some(sha256) => sha256,
+        None => return Ok(admin_form_error("A SHA-256 hash is 64 hexadecimal digits.")),
+    };
+
+    // Banning a file twice keeps the first entry
+    sqlx::query(
+        r#"INSERT INTO banned_files (sha256, reason, created_by, created_at)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (sha256) DO NOTHING"#,
+    )
+    .bind(&sha256)
+    .bind(form.reason.trim())
+    .bind(&admin.username)
+    .bind(Utc::now().timestamp())
+    .execute(pool.get_ref())
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/admin/files"))
+        .finish())
+}
+
+// ADMIN: Remove a file hash from the blocklist
+async fn admin_unban_file_form(
+    _admin: AdminUser,
+    csrf: CsrfToken,
+    path: web::Path<(i32,)>,
+) -> HttpResponse {
+    let id = path.into_inner().0;
+    let action_url = format!("/admin/files/lift/{}", id);
+    let prompt = format!("Allow banned file {} again?", id);
+    let html = render_confirm_prompt(&action_url, "Lift File Ban", &prompt, &csrf);
+    HttpResponse::Ok().content_type("text/html").body(html)
+}
+
+async fn admin_unban_file_action(
+    _admin: AdminUser,
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<(i32,)>,
+) -> Result<HttpResponse, Error> {
+    let id = path.into_inner().0;
+
+    sqlx::query("DELETE FROM banned_files WHERE id = $1")
+        .bind(id)
+        .execute(pool.get_ref())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/admin/files"))
+        .finish())
+}
+
+// ADMIN: Ban the image on a post by its perceptual hash, then delete the post
+async fn admin_ban_image_form(
+    _admin: AdminUser,
+    csrf: CsrfToken,
+    path: web::Path<(String, i32)>,
+) -> HttpResponse {
+    let (kind, id) = path.into_inner();
+    let target = match ReportTarget::parse(&kind, id) {
+        Some(target) => target,
+        None => return report_not_found(),
+    };
+
+    let action_url = format!("/admin/ban-image/{}/{}", target.kind(), target.id());
+    let prompt = match target {
+        ReportTarget::Thread(_) => {
+            "Ban this image and delete the thread with all of its replies? Uploads that look like it will be refused."
+        }
+        ReportTarget::Reply(_) => {
+            "Ban this image and delete the reply? Uploads that look like it will be refused."
+        }
+    };
+    let html = render_confirm_prompt(&action_url, "Ban Image", prompt, &csrf);
+    HttpResponse::Ok().content_type("text/html").body(html)
+}
+
+async fn admin_ban_image_action(
+    admin: AdminUser,
+    pool: web::Data<Pool<Postgres>>,
+    registry: web::Data<BoardRegistry>,
+    path: web::Path<(String, i32)>,
+) -> Result<HttpResponse, Error> {
+    let (kind, id) = path.into_inner();
+    let target = match ReportTarget::parse(&kind, id) {
+        Some(target) => target,
+        None => return Ok(report_not_found()),
+    };
+    let (thread_id, board_id) = match target
+        .locate(pool.get_ref())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+    {
+        Some(location) => location,
+        None => return Ok(report_not_found()),
+    };
+
+    let table = match target {
+        ReportTarget::Thread(_) => "threads",
+        ReportTarget::Reply(_) => "replies",
+    };
+    let (post_no, media_url, media_type, stored_phash): (i32, Option<String>, Option<String>, Option<i64>) =
+        sqlx::query_as(&format!(
+            "SELECT post_no, media_url, media_type, media_phash FROM {} WHERE id = $1",
+            table
+        ))
+        .bind(target.id())
+        .fetch_one(pool.get_ref())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    // Posts from before perceptual hashes were recorded are hashed from their file
+    let phash = match (media_url.as_deref(), media_type.as_deref()) {
+        (Some(url), Some("image")) => match (stored_phash, upload::media_path(url)) {
+            (Some(phash), _) => Some(phash),
+            (None, Some(path)) => web::block(move || hashes::perceptual_hash_of_file(&path))
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?,
+            (None, None) => None,
+        },
+        _ => return Ok(admin_form_error("This post has no image to ban.")),
+    };
+    let phash = match phash {
+        Some(phash) => phash,
+        None => return Ok(admin_form_error("The image file could not be read.")),
+    };
+    if !hashes::is_distinctive(phash) {
+        return Ok(admin_form_error(
+            "This image has too little detail to ban by its appearance. Ban the file instead.",
+        ));
+    }
+
+    let board = registry.get(board_id);
+    let reason = match &board {
+        Some(board) => format!("No. {} on /{}/", post_no, board.uri),
+        None => format!("No. {}", post_no),
+    };
+    sqlx::query(
+        r#"INSERT INTO banned_images (phash, reason, created_by, created_at)
+        VALUES ($1, $2, $3, $4)"#,
+    )
+    .bind(phash)
+    .bind(&reason)
+    .bind(&admin.username)
+    .bind(Utc::now().timestamp())
+    .execute(pool.get_ref())
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let redirect_url = match target {
+        ReportTarget::Thread(id) => {
+            media::delete_thread(pool.get_ref(), id)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+            board.map_or_else(|| "/".to_string(), |board| format!("/{}/", board.uri))
+        }
+        ReportTarget::Reply(id) => {
+            media::delete_reply(pool.get_ref(), id)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+            format!("/thread/{}", thread_id)
+        }
+    };
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, redirect_url))
+        .finish())
+}
+
+// ADMIN: Remove an image from the blocklist
+async fn admin_unban_image_form(
+    _admin: AdminUser,
+    csrf: CsrfToken,
+    path: web::Path<(i32,)>,
+) -> HttpResponse {
+    let id = path.into_inner().0;
+    let action_url = format!("/admin/images/lift/{}", id);
+    let prompt = format!("Allow banned image {} again?", id);
+    let html = render_confirm_prompt(&action_url, "Lift Image Ban", &prompt, &csrf);
+    HttpResponse::Ok().content_type("text/html").body(html)
+}
+
+async fn admin_unban_image_action(
+    _admin: AdminUser,
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<(i32,)>,
+) -> Result<HttpResponse, Error> {
+    let id = path.into_inner().0;
+
+    sqlx::query("DELETE FROM banned_images WHERE id = $1")
+        .bind(id)
+        .execute(pool.get_ref())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/admin/files"))
+        .finish())
+}
+
+// ADMIN: Report queue, one row per reported post
+async fn admin_reports(
+    _admin: AdminUser,
+    pool: web::Data<Pool<Postgres>>,
+) -> Result<HttpResponse, Error> {
+    let queue = reports::queue(pool.get_ref())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let rows = if queue.is_empty() {
+        "<p>No open reports.</p>".to_string()
+    } else {
+        let rows = queue
+            .iter()
+            .map(|entry| {
+                let target = entry.target();
+                let reasons = entry
+                    .reasons
+                    .iter()
+                    .map(|reason| escape_html(reason))
+                    .collect::<Vec<String>>()
+                    .join("<br>");
+                // Keep long posts from swamping the table
+                let excerpt: String = entry.message.chars().take(200).collect();
+                format!(
+                    r#"<tr><td><a href="/thread/{thread}">{kind} {id}</a></td><td>{excerpt}</td><td>{count}</td><td>{reasons}</td><td>{last}</td><td><a href="/admin/reports/dismiss/{kind}/{id}">[dismiss]</a> <a href="/admin/{kind}/delete/{id}?next=/admin/reports">[delete]</a> <a href="/admin/bans?{kind}={id}">[ban]</a></td></tr>"#,
+                    thread = entry.parent_thread_id,
+                    kind = target.kind(),
+                    id = target.id(),
+                    excerpt = escape_html(&excerpt),
+                    count = entry.report_count,
+                    reasons = reasons,
+                    last = format_timestamp(entry.last_reported),
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        format!(
+            r#"<table class="admin-table">
+        <tr><th>Post</th><th>Message</th><th>Reports</th><th>Reasons</th><th>Last reported</th><th></th></tr>
+        {}
+    </table>"#,
+            rows
+        )
+    };
+
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Reports</title>
+    <link rel="stylesheet" href="/static/style.css">
+</head>
+<body>
+    <h1>Reports</h1>
+    {}
+    <p><a href="/admin">[Admin]</a> <a href="/">[Home]</a></p>
+</body>
+</html>"#,
+        rows
+    );
+    Ok(HttpResponse::Ok().content_type("text/html").body(html))
+}
+
+// ADMIN: Dismiss the reports about a post
+async fn admin_dismiss_reports_form(
+    _admin: AdminUser,
+    csrf: CsrfToken,
+    path: web::Path<(String, i32)>,
+) -> HttpResponse {
+    let (kind, id) = path.into_inner();
+    let target = match ReportTarget::parse(&kind, id) {
+        Some(target) => target,
+        None => return report_not_found(),
+    };
+
+    let action_url = format!("/admin/reports/dismiss/{}/{}", target.kind(), target.id());
+    let prompt = format!(
+        "Dismiss all reports about {} {}? The post stays up.",
+        target.kind(),
+        target.id()
+    );
+    let html = render_confirm_prompt(&action_url, "Dismiss Reports", &prompt, &csrf);
+    HttpResponse::Ok().content_type("text/html").body(html)
+}
+
+async fn admin_dismiss_reports_action(
+    _admin: AdminUser,
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<(String, i32)>,
+) -> Result<HttpResponse, Error> {
+    let (kind, id) = path.into_inner();
+    let target = match ReportTarget::parse(&kind, id) {
+        Some(target) => target,
+        None => return Ok(report_not_found()),
+    };
+
+    reports::dismiss(pool.get_ref(), target)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/admin/reports"))
         .finish())
 }
 
@@ -901,42 +2878,141 @@ async fn main() -> std::io::Result<()> {
     dotenv().ok();
     env_logger::init();
 
-    for dir in &[IMAGE_UPLOAD_DIR, VIDEO_UPLOAD_DIR, IMAGE_THUMB_DIR] {
-        if !std::path::Path::new(dir).exists() {
+    // Settings come from config.toml and CHESS_* variables, which .env may also set
+    let config = Config::load().unwrap_or_else(|e| {
+        eprintln!("Invalid configuration: {}", e);
+        std::process::exit(1);
+    });
+    config::init(config);
+    let config = config::get();
+
+    let paths = &config.paths;
+    for dir in [&paths.image_uploads, &paths.video_uploads, &paths.image_thumbs] {
+        if !dir.exists() {
             std::fs::create_dir_all(dir).ok();
         }
     }
 
     let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let pool = Pool::<Postgres>::connect(&database_url)
+    let pool = PgPoolOptions::new()
+        .max_connections(config.database.max_connections)
+        .connect(&database_url)
         .await
         .expect("Failed to connect to DB");
 
+    // Bring the schema up to date; applied versions are tracked in _sqlx_migrations
+    sqlx::migrate!("./migrations")
+        .run(&pool)
+        .await
+        .expect("Failed to run database migrations");
+
+    // Maintenance subcommands (e.g. `chess_board admin set <username>`) run instead of the server
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return cli::run(&pool, &args).await;
+    }
+
+    auth::check_admin_accounts(&pool)
+        .await
+        .expect("Failed to read admin accounts");
+
+    media::spawn_gc_task(pool.clone());
+
+    let registry = web::Data::new(
+        BoardRegistry::load(&pool)
+            .await
+            .expect("Failed to load boards"),
+    );
+
+    // The config refuses keys shorter than the 64 bytes `Key::from` needs
+    let session_key = Key::from(config.secrets.session_key.as_bytes());
+    let cookie_secure = config.server.cookie_secure;
+
+    let identifier = web::Data::new(PosterIdentifier::from_config());
+    let limiter = web::Data::new(RateLimiter::default());
+
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(pool.clone()))
-            .wrap(middleware::Logger::default())
-            .service(fs::Files::new("/static", "./static"))
-            .service(fs::Files::new("/uploads/images", IMAGE_UPLOAD_DIR))
-            .service(fs::Files::new("/uploads/videos", VIDEO_UPLOAD_DIR))
-            .service(fs::Files::new("/thumbs/images", IMAGE_THUMB_DIR))
+            .app_data(registry.clone())
+            .app_data(identifier.clone())
+            .app_data(limiter.clone())
+            // Runs inside the session middleware, which is registered after it
+            .wrap(middleware::from_fn(csrf::verify))
+            .wrap(
+                SessionMiddleware::builder(CookieSessionStore::default(), session_key.clone())
+                    .cookie_secure(cookie_secure)
+                    .build(),
+            )
+            // The default format logs the whole request line; log the path only, so the
+            // CSRF tokens that pages from older versions put in form actions stay out of it
+            .wrap(
+                middleware::Logger::new(r#"%a "%{method}xi %U" %s %b "%{Referer}i" "%{User-Agent}i" %T"#)
+                    .custom_request_replace("method", |req| req.method().to_string()),
+            )
+            .service(fs::Files::new("/static", &paths.static_dir))
+            .service(fs::Files::new("/uploads/images", &paths.image_uploads))
+            .service(fs::Files::new("/uploads/videos", &paths.video_uploads))
+            .service(fs::Files::new("/thumbs/images", &paths.image_thumbs))
             // Public routes
             .route("/", web::get().to(homepage))
             .route("/board/{id}", web::get().to(board_page))
             .route("/board/{id}/thread", web::post().to(create_thread))
+            .route("/board/{id}/archive", web::get().to(board_archive))
+            .route("/board/{id}/catalog", web::get().to(catalog::catalog))
+            .route("/board/{id}/feed.atom", web::get().to(feeds::board_feed))
+            .route("/search", web::get().to(search::search))
             .route("/thread/{id}", web::get().to(view_thread))
+            .route("/thread/{id}/feed.atom", web::get().to(feeds::thread_feed))
             .route("/reply", web::post().to(create_reply))
+            .route("/report/{kind}/{id}", web::get().to(report_form))
+            .route("/report/{kind}/{id}", web::post().to(report_action))
+            // JSON API
+            .route("/api/boards", web::get().to(api::boards))
+            .route("/api/board/{id}", web::get().to(api::board_threads))
+            .route("/api/board/{id}/catalog", web::get().to(api::catalog))
+            .route("/api/thread/{id}", web::get().to(api::thread))
+            // Admin session
+            .route("/admin", web::get().to(admin_index))
+            .route("/admin/login", web::get().to(admin_login_form))
+            .route("/admin/login", web::post().to(admin_login_action))
+            .route("/admin/logout", web::post().to(admin_logout))
             // Admin routes for deletion and edit
             .route("/admin/thread/delete/{id}", web::get().to(admin_delete_thread_form))
             .route("/admin/thread/delete/{id}", web::post().to(admin_delete_thread_action))
+            .route("/admin/thread/{action}/{id}", web::get().to(admin_thread_flag_form))
+            .route("/admin/thread/{action}/{id}", web::post().to(admin_thread_flag_action))
             .route("/admin/reply/delete/{id}", web::get().to(admin_delete_reply_form))
             .route("/admin/reply/delete/{id}", web::post().to(admin_delete_reply_action))
+            .route("/admin/boards", web::get().to(admin_boards))
+            .route("/admin/boards/create", web::post().to(admin_create_board_action))
             .route("/admin/boards/delete/{id}", web::get().to(admin_delete_board_form))
             .route("/admin/boards/delete/{id}", web::post().to(admin_delete_board_action))
             .route("/admin/boards/edit/{id}", web::get().to(admin_edit_board_form))
             .route("/admin/boards/edit/{id}", web::post().to(admin_edit_board_action))
+            .route("/admin/boards/restore/{id}", web::get().to(admin_restore_board_form))
+            .route("/admin/boards/restore/{id}", web::post().to(admin_restore_board_action))
+            .route("/admin/boards/purge/{id}", web::get().to(admin_purge_board_form))
+            .route("/admin/boards/purge/{id}", web::post().to(admin_purge_board_action))
+            .route("/admin/reports", web::get().to(admin_reports))
+            .route("/admin/reports/dismiss/{kind}/{id}", web::get().to(admin_dismiss_reports_form))
+            .route("/admin/reports/dismiss/{kind}/{id}", web::post().to(admin_dismiss_reports_action))
+            .route("/admin/bans", web::get().to(admin_bans))
+            .route("/admin/bans/create", web::post().to(admin_create_ban_action))
+            .route("/admin/bans/lift/{id}", web::get().to(admin_lift_ban_form))
+            .route("/admin/bans/lift/{id}", web::post().to(admin_lift_ban_action))
+            .route("/admin/files", web::get().to(admin_banned_files))
+            .route("/admin/files/create", web::post().to(admin_ban_file_action))
+            .route("/admin/files/lift/{id}", web::get().to(admin_unban_file_form))
+            .route("/admin/files/lift/{id}", web::post().to(admin_unban_file_action))
+            .route("/admin/ban-image/{kind}/{id}", web::get().to(admin_ban_image_form))
+            .route("/admin/ban-image/{kind}/{id}", web::post().to(admin_ban_image_action))
+            .route("/admin/images/lift/{id}", web::get().to(admin_unban_image_form))
+            .route("/admin/images/lift/{id}", web::post().to(admin_unban_image_action))
+            // Short board URIs such as /kg/ go last so they never shadow the routes above
+            .route("/{uri}/", web::get().to(board_page_by_uri))
     })
-    .bind(("0.0.0.0", 8080))?
+    .bind(config.server.listen.as_str())?
     .run()
     .await
 }
@@ -0,0 +1,561 @@
+// src/markup.rs
+//
+// Turns raw message bodies into HTML. The message is split into tokens first and every
+// piece of user text is escaped on its way out, so markup can only ever add the tags
+// emitted here.
+//
+// Supported markup:
+//   >>123, >>>/uri/123   links to posts
+//   >text                greentext, for lines that are not quotes
+//   [spoiler]..[/spoiler] hidden until hovered; must open and close on the same line
+//   [code]..[/code]      preformatted block, no other markup applies inside
+//   http(s)://...        links, marked nofollow
+
+use crate::board::BoardRegistry;
+use crate::escape_html;
+use sqlx::{Pool, Postgres};
+use std::collections::{HashMap, HashSet};
+
+/// A `>>N` or `>>>/uri/N` reference to another post.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Quote {
+    /// `None` for `>>N`, which refers to the board the message was posted on.
+    pub board: Option<String>,
+    pub post_no: i32,
+}
+
+enum Token<'a> {
+    Text(&'a str),
+    Quote { quote: Quote, raw: &'a str },
+    Link(&'a str),
+}
+
+/// Top-level pieces of a message: code blocks, which are shown verbatim, and
+/// everything else.
+enum Block<'a> {
+    Text(&'a str),
+    Code(&'a str),
+}
+
+const CODE_OPEN: &str = "[code]";
+const CODE_CLOSE: &str = "[/code]";
+const SPOILER_OPEN: &str = "[spoiler]";
+const SPOILER_CLOSE: &str = "[/spoiler]";
+
+/// Characters that end a URL. Quotes and angle brackets are excluded so a link can
+/// never swallow surrounding markup, even though the href is escaped anyway.
+fn ends_url(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '"' | '\'' | '<' | '>' | '[' | ']')
+}
+
+/// Matches an http(s) URL at the start of `s`, returning its length in bytes.
+/// Trailing punctuation is left out, so "see https://example.com." links without the dot.
+fn url_at(s: &str) -> Option<usize> {
+    let scheme = ["http://", "https://"]
+        .into_iter()
+        .find(|scheme| s.starts_with(scheme))?;
+    let len = s.find(ends_url).unwrap_or(s.len());
+    let url = s[..len].trim_end_matches(['.', ',', ';', ':', '!', '?', ')']);
+    (url.len() > scheme.len()).then_some(url.len())
+}
+
+fn split_blocks(message: &str) -> Vec<Block<'_>> {
+    let mut blocks = Vec::new();
+    let mut rest = message;
+
+    while let Some(start) = rest.find(CODE_OPEN) {
+        let after_open = &rest[start + CODE_OPEN.len()..];
+        // An unclosed [code] is left as plain text
+        let Some(end) = after_open.find(CODE_CLOSE) else {
+            break;
+        };
+        if start > 0 {
+            let text = &rest[..start];
+            blocks.push(Block::Text(text.strip_suffix('\n').unwrap_or(text)));
+        }
+        let code = &after_open[..end];
+        let code = code.strip_prefix('\n').unwrap_or(code);
+        let code = code.strip_suffix('\n').unwrap_or(code);
+        blocks.push(Block::Code(code));
+
+        rest = &after_open[end + CODE_CLOSE.len()..];
+        // The block already breaks the line, so drop the newline that follows the tag
+        rest = rest.strip_prefix('\n').unwrap_or(rest);
+    }
+    if !rest.is_empty() {
+        blocks.push(Block::Text(rest));
+    }
+    blocks
+}
+
+/// Parses the digits at the start of `s`, returning the number and the bytes consumed.
+fn leading_post_no(s: &str) -> Option<(i32, usize)> {
+    let len = s.bytes().take_while(u8::is_ascii_digit).count();
+    let post_no = s[..len].parse::<i32>().ok().filter(|n| *n > 0)?;
+    Some((post_no, len))
+}
+
+/// Matches a quote at the start of `s`, returning it and its length in bytes.
+fn quote_at(s: &str) -> Option<(Quote, usize)> {
+    if let Some(rest) = s.strip_prefix(">>>/") {
+        let uri_len = rest
+            .bytes()
+            .take_while(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+            .count();
+        if (1..=16).contains(&uri_len) {
+            if let Some(after) = rest[uri_len..].strip_prefix('/') {
+                if let Some((post_no, len)) = leading_post_no(after) {
+                    let quote = Quote {
+                        board: Some(rest[..uri_len].to_string()),
+                        post_no,
+                    };
+                    return Some((quote, 4 + uri_len + 1 + len));
+                }
+            }
+        }
+    }
+
+    let rest = s.strip_prefix(">>")?;
+    let (post_no, len) = leading_post_no(rest)?;
+    Some((Quote { board: None, post_no }, 2 + len))
+}
+
+fn tokenize(message: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut text_start = 0;
+    let mut i = 0;
+
+    while i < message.len() {
+        let matched = match message.as_bytes()[i] {
+            b'>' => quote_at(&message[i..]).map(|(quote, len)| {
+                let raw = &message[i..i + len];
+                (Token::Quote { quote, raw }, len)
+            }),
+            // Only start a link at a word boundary, so "xhttp://" stays text
+            b'h' if !message[..i].ends_with(|c: char| c.is_alphanumeric()) => {
+                url_at(&message[i..]).map(|len| (Token::Link(&message[i..i + len]), len))
+            }
+            _ => None,
+        };
+        if let Some((token, len)) = matched {
+            if text_start < i {
+                tokens.push(Token::Text(&message[text_start..i]));
+            }
+            tokens.push(token);
+            i += len;
+            text_start = i;
+            continue;
+        }
+        // Tokens start with an ASCII byte, so stepping over whole characters is enough
+        i += message[i..].chars().next().map_or(1, char::len_utf8);
+    }
+    if text_start < message.len() {
+        tokens.push(Token::Text(&message[text_start..]));
+    }
+    tokens
+}
+
+/// Every post reference in a message, in order of appearance.
+/// Quotes inside code blocks are not references.
+pub fn quotes(message: &str) -> Vec<Quote> {
+    split_blocks(message)
+        .into_iter()
+        .filter_map(|block| match block {
+            Block::Text(text) => Some(text),
+            Block::Code(_) => None,
+        })
+        .flat_map(tokenize)
+        .filter_map(|token| match token {
+            Token::Quote { quote, .. } => Some(quote),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Post numbers mapped to the thread that contains them, so quotes can be linked.
+/// Numbers that do not resolve (deleted posts, unknown boards) render as dead quotes.
+#[derive(Default)]
+pub struct QuoteTargets {
+    threads: HashMap<(String, i32), i32>,
+}
+
+impl QuoteTargets {
+    /// Looks up every post quoted in `messages`, which were posted on the board `home_uri`.
+    pub async fn resolve<'a, I>(
+        pool: &Pool<Postgres>,
+        registry: &BoardRegistry,
+        home_uri: &str,
+        messages: I,
+    ) -> Result<Self, sqlx::Error>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut wanted: HashMap<String, Vec<i32>> = HashMap::new();
+        for message in messages {
+            for quote in quotes(message) {
+                let uri = quote.board.unwrap_or_else(|| home_uri.to_string());
+                wanted.entry(uri).or_default().push(quote.post_no);
+            }
+        }
+
+        let mut targets = QuoteTargets::default();
+        for (uri, post_nos) in wanted {
+            let board = match registry.get_by_uri(&uri) {
+                Some(board) => board,
+                None => continue,
+            };
+            let found: Vec<(i32, i32)> = sqlx::query_as(
+                r#"SELECT post_no, id FROM threads WHERE board_id = $1 AND post_no = ANY($2)
+                UNION ALL
+                SELECT r.post_no, r.thread_id FROM replies r
+                JOIN threads t ON t.id = r.thread_id
+                WHERE t.board_id = $1 AND r.post_no = ANY($2)"#,
+            )
+            .bind(board.id)
+            .bind(&post_nos)
+            .fetch_all(pool)
+            .await?;
+
+            for (post_no, thread_id) in found {
+                targets.threads.insert((uri.clone(), post_no), thread_id);
+            }
+        }
+        Ok(targets)
+    }
+
+    fn thread_for(&self, uri: &str, post_no: i32) -> Option<i32> {
+        self.threads.get(&(uri.to_string(), post_no)).copied()
+    }
+}
+
+fn render_quote(quote: &Quote, raw: &str, home_uri: &str, targets: &QuoteTargets) -> String {
+    let uri = quote.board.as_deref().unwrap_or(home_uri);
+    match targets.thread_for(uri, quote.post_no) {
+        Some(thread_id) => format!(
+            r#"<a class="quotelink" href="/thread/{}#p{}">{}</a>"#,
+            thread_id,
+            quote.post_no,
+            escape_html(raw)
+        ),
+        None => format!(r#"<span class="quotelink dead">{}</span>"#, escape_html(raw)),
+    }
+}
+
+fn render_tokens(text: &str, home_uri: &str, targets: &QuoteTargets) -> String {
+    tokenize(text)
+        .into_iter()
+        .map(|token| match token {
+            Token::Text(text) => escape_html(text),
+            Token::Quote { quote, raw } => render_quote(&quote, raw, home_uri, targets),
+            Token::Link(url) => format!(
+                r#"<a href="{}" rel="nofollow noopener" target="_blank">{}</a>"#,
+                escape_html(url),
+                escape_html(url)
+            ),
+        })
+        .collect()
+}
+
+// Renders one line, turning each [spoiler]..[/spoiler] pair into a span.
+// Tags without a partner on the same line are shown as typed.
+fn render_line(line: &str, home_uri: &str, targets: &QuoteTargets) -> String {
+    let mut html = String::new();
+    let mut rest = line;
+
+    while let Some(start) = rest.find(SPOILER_OPEN) {
+        let after_open = &rest[start + SPOILER_OPEN.len()..];
+        let Some(end) = after_open.find(SPOILER_CLOSE) else {
+            break;
+        };
+        html.push_str(&render_tokens(&rest[..start], home_uri, targets));
+        html.push_str(r#"<span class="spoiler">"#);
+        html.push_str(&render_tokens(&after_open[..end], home_uri, targets));
+        html.push_str("</span>");
+        rest = &after_open[end + SPOILER_CLOSE.len()..];
+    }
+    html.push_str(&render_tokens(rest, home_uri, targets));
+
+    // A line starting with > is greentext, unless the > begins a quote
+    if line.starts_with('>') && quote_at(line).is_none() {
+        format!(r#"<span class="greentext">{}</span>"#, html)
+    } else {
+        html
+    }
+}
+
+/// Renders a message posted on `home_uri` as HTML.
+pub fn render_message(message: &str, home_uri: &str, targets: &QuoteTargets) -> String {
+    let message = message.replace("\r\n", "\n");
+
+    split_blocks(&message)
+        .into_iter()
+        .map(|block| match block {
+            Block::Text(text) => text
+                .split('\n')
+                .map(|line| render_line(line, home_uri, targets))
+                .collect::<Vec<String>>()
+                .join("<br>"),
+            Block::Code(code) => format!(r#"<pre class="code">{}</pre>"#, escape_html(code)),
+        })
+        .collect()
+}
+
+/// For each post in a thread, the later posts of the same thread that quote it.
+/// `posts` holds `(post_no, message)` pairs in posting order.
+pub fn backlinks(home_uri: &str, posts: &[(i32, &str)]) -> HashMap<i32, Vec<i32>> {
+    let in_thread: HashSet<i32> = posts.iter().map(|(n, _)| *n).collect();
+    let mut backlinks: HashMap<i32, Vec<i32>> = HashMap::new();
+
+    for (post_no, message) in posts {
+        for quote in quotes(message) {
+            let same_board = quote.board.as_deref().is_none_or(|uri| uri == home_uri);
+            if !same_board || quote.post_no == *post_no || !in_thread.contains(&quote.post_no) {
+                continue;
+            }
+            let list = backlinks.entry(quote.post_no).or_default();
+            if !list.contains(post_no) {
+                list.push(*post_no);
+            }
+        }
+    }
+    backlinks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every tag the renderer is allowed to produce. Anything else starting with `<`
+    // in the output means user input got through unescaped.
+    const ALLOWED_TAGS: &[&str] = &[
+        "<br>",
+        "<span class=\"greentext\">",
+        "<span class=\"spoiler\">",
+        "<span class=\"quotelink dead\">",
+        "</span>",
+        "<pre class=\"code\">",
+        "</pre>",
+        "<a class=\"quotelink\" href=\"/thread/",
+        "<a href=\"http",
+        "</a>",
+    ];
+
+    const HOSTILE_INPUTS: &[&str] = &[
+        "<script>alert(1)</script>",
+        "<img src=x onerror=alert(1)>",
+        "\"><script>alert(1)</script>",
+        "' onmouseover='alert(1)",
+        ">greentext <script>alert(1)</script>",
+        "[spoiler]<b>bold</b>[/spoiler]",
+        "[spoiler]</span><script>alert(1)</script>[/spoiler]",
+        "[code]</pre><script>alert(1)</script>[/code]",
+        "[code]<b>unclosed",
+        "[spoiler][spoiler]nested[/spoiler][/spoiler]",
+        "[/spoiler][spoiler]",
+        "https://example.com/\"onmouseover=\"alert(1)",
+        "https://example.com/'><script>alert(1)</script>",
+        "https://example.com/<script>",
+        "javascript:alert(1)",
+        "<a href=\"javascript:alert(1)\">x</a>",
+        ">>1\"><script>alert(1)</script>",
+        ">>>/a\"b/1",
+        "&lt;script&gt;",
+        "line one\r\n<br onclick=alert(1)>\r\nline three",
+    ];
+
+    fn render(message: &str) -> String {
+        render_message(message, "b", &QuoteTargets::default())
+    }
+
+    fn targets(posts: &[(&str, i32, i32)]) -> QuoteTargets {
+        let mut targets = QuoteTargets::default();
+        for (uri, post_no, thread_id) in posts {
+            targets
+                .threads
+                .insert((uri.to_string(), *post_no), *thread_id);
+        }
+        targets
+    }
+
+    fn assert_only_allowed_tags(html: &str) {
+        for (i, _) in html.match_indices('<') {
+            assert!(
+                ALLOWED_TAGS.iter().any(|tag| html[i..].starts_with(tag)),
+                "unexpected tag at {} in {:?}",
+                i,
+                html
+            );
+        }
+    }
+
+    fn assert_balanced(html: &str) {
+        let opened = html.matches("<span").count();
+        assert_eq!(opened, html.matches("</span>").count(), "{:?}", html);
+        assert_eq!(html.matches("<a ").count(), html.matches("</a>").count(), "{:?}", html);
+        assert_eq!(html.matches("<pre").count(), html.matches("</pre>").count(), "{:?}", html);
+    }
+
+    #[test]
+    fn hostile_input_cannot_inject_html() {
+        for input in HOSTILE_INPUTS {
+            let html = render(input);
+            assert_only_allowed_tags(&html);
+            assert_balanced(&html);
+            assert!(!html.contains("<script"), "{:?}", html);
+        }
+    }
+
+    #[test]
+    fn hostile_input_cannot_inject_html_through_resolved_quotes() {
+        let targets = targets(&[("b", 1, 7), ("a", 1, 8)]);
+        for input in HOSTILE_INPUTS {
+            let html = render_message(input, "b", &targets);
+            assert_only_allowed_tags(&html);
+            assert_balanced(&html);
+        }
+    }
+
+    #[test]
+    fn hostile_input_cannot_inject_html_in_combination() {
+        // Glue the inputs together in pairs so tags from one can interact with the next
+        for first in HOSTILE_INPUTS {
+            for second in HOSTILE_INPUTS {
+                for separator in ["", " ", "\n"] {
+                    let html = render(&format!("{}{}{}", first, separator, second));
+                    assert_only_allowed_tags(&html);
+                    assert_balanced(&html);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn link_attributes_cannot_be_broken_out_of() {
+        let html = render("https://example.com/\"onmouseover=\"alert(1)");
+        assert_eq!(
+            html,
+            "<a href=\"https:&#x2F;&#x2F;example.com&#x2F;\" rel=\"nofollow noopener\" \
+             target=\"_blank\">https:&#x2F;&#x2F;example.com&#x2F;</a>&quot;onmouseover=&quot;alert(1)"
+        );
+    }
+
+    #[test]
+    fn plain_text_is_escaped() {
+        assert_eq!(render("a < b && c"), "a &lt; b &amp;&amp; c");
+    }
+
+    #[test]
+    fn newlines_become_line_breaks() {
+        assert_eq!(render("one\ntwo\r\nthree"), "one<br>two<br>three");
+        assert_eq!(render("one\n\nthree"), "one<br><br>three");
+    }
+
+    #[test]
+    fn greentext_lines() {
+        assert_eq!(
+            render("hello\n>be me\nbye"),
+            "hello<br><span class=\"greentext\">&gt;be me</span><br>bye"
+        );
+        // Only a > at the start of a line counts
+        assert_eq!(render("a > b"), "a &gt; b");
+    }
+
+    #[test]
+    fn quotes_are_not_greentext() {
+        assert_eq!(
+            render(">>12 agreed"),
+            "<span class=\"quotelink dead\">&gt;&gt;12</span> agreed"
+        );
+        assert_eq!(
+            render(">>not a quote"),
+            "<span class=\"greentext\">&gt;&gt;not a quote</span>"
+        );
+    }
+
+    #[test]
+    fn quotes_link_to_their_thread() {
+        let targets = targets(&[("b", 12, 3), ("g", 5, 9)]);
+        assert_eq!(
+            render_message(">>12 and >>>/g/5", "b", &targets),
+            "<a class=\"quotelink\" href=\"/thread/3#p12\">&gt;&gt;12</a> and \
+             <a class=\"quotelink\" href=\"/thread/9#p5\">&gt;&gt;&gt;&#x2F;g&#x2F;5</a>"
+        );
+    }
+
+    #[test]
+    fn spoilers() {
+        assert_eq!(
+            render("it was [spoiler]him[/spoiler] all along"),
+            "it was <span class=\"spoiler\">him</span> all along"
+        );
+        assert_eq!(
+            render("[spoiler]a[/spoiler] [spoiler]b[/spoiler]"),
+            "<span class=\"spoiler\">a</span> <span class=\"spoiler\">b</span>"
+        );
+    }
+
+    #[test]
+    fn unmatched_spoiler_tags_stay_as_text() {
+        assert_eq!(render("[spoiler]open"), "[spoiler]open");
+        assert_eq!(render("close[/spoiler]"), "close[&#x2F;spoiler]");
+        // Spoilers do not span lines
+        assert_eq!(render("[spoiler]a\nb[/spoiler]"), "[spoiler]a<br>b[&#x2F;spoiler]");
+    }
+
+    #[test]
+    fn spoilers_inside_greentext() {
+        assert_eq!(
+            render(">it was [spoiler]him[/spoiler]"),
+            "<span class=\"greentext\">&gt;it was <span class=\"spoiler\">him</span></span>"
+        );
+    }
+
+    #[test]
+    fn code_blocks_keep_whitespace_and_skip_markup() {
+        assert_eq!(
+            render("look:\n[code]\nfn f() {\n    >>1 [spoiler]x[/spoiler]\n}\n[/code]\nneat"),
+            "look:<pre class=\"code\">fn f() {\n    &gt;&gt;1 [spoiler]x[&#x2F;spoiler]\n}</pre>neat"
+        );
+    }
+
+    #[test]
+    fn unclosed_code_block_stays_as_text() {
+        assert_eq!(render("[code]x\ny"), "[code]x<br>y");
+    }
+
+    #[test]
+    fn quotes_inside_code_blocks_are_ignored() {
+        assert_eq!(quotes("[code]>>1[/code] >>2"), vec![Quote { board: None, post_no: 2 }]);
+    }
+
+    #[test]
+    fn urls_are_linked() {
+        assert_eq!(
+            render("see https://example.com/a?b=1&c=2."),
+            "see <a href=\"https:&#x2F;&#x2F;example.com&#x2F;a?b=1&amp;c=2\" \
+             rel=\"nofollow noopener\" target=\"_blank\">https:&#x2F;&#x2F;example.com&#x2F;a?b=1&amp;c=2</a>."
+        );
+    }
+
+    #[test]
+    fn only_http_urls_are_linked() {
+        let inputs = [
+            "javascript:alert(1)",
+            "ftp://example.com",
+            "data:text/html,x",
+            "http://",
+            "xhttp://a.b",
+        ];
+        for input in inputs {
+            assert!(!render(input).contains("<a"), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn non_ascii_text_is_kept() {
+        assert_eq!(
+            render("héllo >>1 wörld"),
+            "héllo <span class=\"quotelink dead\">&gt;&gt;1</span> wörld"
+        );
+    }
+}
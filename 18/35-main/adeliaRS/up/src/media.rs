@@ -0,0 +1,324 @@
+// src/media.rs
+
+use crate::board::Board;
+use crate::upload::{self, SavedMedia};
+use crate::config;
+use sqlx::{Pool, Postgres, Transaction};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// Files younger than this are never collected, so uploads that are still
+/// being written (and not yet inserted into the database) are left alone.
+const GC_MIN_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Removes the files behind media and thumbnail URLs. Missing files are ignored.
+pub fn remove_files<I>(urls: I)
+where
+    I: IntoIterator<Item = Option<String>>,
+{
+    for url in urls.into_iter().flatten() {
+        if let Some(path) = upload::media_path(&url) {
+            if let Err(e) = std::fs::remove_file(&path) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("Failed to remove {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+}
+
+// Transaction-scoped lock on a stored file's URL. Releasing a file and a new post
+// taking up a shared one both hold it, so one always sees what the other did.
+async fn lock_file(tx: &mut Transaction<'_, Postgres>, url: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+        .bind(url)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+/// Removes the files behind media and thumbnail URLs of deleted posts, except those
+/// another post still uses. Identical uploads share one file, so it is only removed
+/// along with the last post referring to it.
+pub async fn release_files<I>(pool: &Pool<Postgres>, urls: I) -> Result<(), sqlx::Error>
+where
+    I: IntoIterator<Item = Option<String>>,
+{
+    let mut urls: Vec<String> = urls.into_iter().flatten().collect();
+    if urls.is_empty() {
+        return Ok(());
+    }
+    // Locks are taken in a fixed order so two deletes never wait on each other
+    urls.sort();
+    urls.dedup();
+
+    let mut tx = pool.begin().await?;
+    for url in &urls {
+        lock_file(&mut tx, url).await?;
+    }
+    let in_use: HashSet<String> = sqlx::query_scalar(
+        r#"SELECT url FROM UNNEST($1::TEXT[]) AS url
+        WHERE EXISTS (SELECT 1 FROM threads WHERE media_url = url OR thumb_url = url)
+           OR EXISTS (SELECT 1 FROM replies WHERE media_url = url OR thumb_url = url)"#,
+    )
+    .bind(&urls)
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .collect();
+
+    remove_files(urls.into_iter().filter(|url| !in_use.contains(url)).map(Some));
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Called in the transaction that inserts a post using `media`. For a shared file it
+/// takes the file's lock and checks the file is still there: a delete of the last post
+/// using it either removed it already, or now waits for this post to commit and keeps it.
+pub async fn claim_shared(
+    tx: &mut Transaction<'_, Postgres>,
+    media: &SavedMedia,
+) -> Result<bool, sqlx::Error> {
+    if !media.shared {
+        return Ok(true);
+    }
+    lock_file(tx, &media.url).await?;
+    Ok(upload::media_path(&media.url).is_some_and(|path| path.exists()))
+}
+
+/// Deletes a thread and its replies, then removes their media files unless other posts share them.
+/// Returns `false` if the thread did not exist.
+pub async fn delete_thread(pool: &Pool<Postgres>, thread_id: i32) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let reply_media: Vec<(Option<String>, Option<String>)> =
+        sqlx::query_as("SELECT media_url, thumb_url FROM replies WHERE thread_id = $1")
+            .bind(thread_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+    let thread_media: Option<(Option<String>, Option<String>)> = sqlx::query_as(
+        "DELETE FROM threads WHERE id = $1 RETURNING media_url, thumb_url",
+    )
+    .bind(thread_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    // Files go only after the rows are gone, so a failed delete never leaves broken posts
+    let found = thread_media.is_some();
+    release_files(
+        pool,
+        thread_media
+            .into_iter()
+            .chain(reply_media)
+            .flat_map(|(media, thumb)| [media, thumb]),
+    )
+    .await?;
+    Ok(found)
+}
+
+/// Deletes a reply and its media files (unless shared). Returns the thread it belonged to.
+pub async fn delete_reply(pool: &Pool<Postgres>, reply_id: i32) -> Result<Option<i32>, sqlx::Error> {
+    let deleted: Option<(i32, Option<String>, Option<String>)> = sqlx::query_as(
+        "DELETE FROM replies WHERE id = $1 RETURNING thread_id, media_url, thumb_url",
+    )
+    .bind(reply_id)
+    .fetch_optional(pool)
+    .await?;
+
+    match deleted {
+        Some((thread_id, media, thumb)) => {
+            release_files(pool, [media, thumb]).await?;
+            Ok(Some(thread_id))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Permanently deletes every thread on a board along with its media.
+/// The board row itself is kept so the URI stays reserved.
+pub async fn purge_board(pool: &Pool<Postgres>, board_id: i32) -> Result<usize, sqlx::Error> {
+    let thread_ids: Vec<i32> = sqlx::query_scalar("SELECT id FROM threads WHERE board_id = $1")
+        .bind(board_id)
+        .fetch_all(pool)
+        .await?;
+
+    let mut purged = 0;
+    for thread_id in thread_ids {
+        if delete_thread(pool, thread_id).await? {
+            purged += 1;
+        }
+    }
+    Ok(purged)
+}
+
+/// Enforces a board's `max_threads` after `new_thread_id` is posted on it.
+/// The least recently bumped live threads beyond the limit are archived (locked and
+/// hidden from the index) or deleted with their media, depending on `archive_pruned`.
+/// Pinned threads and the new thread are never pruned. Returns the number of threads pruned.
+pub async fn prune_board(
+    pool: &Pool<Postgres>,
+    board: &Board,
+    new_thread_id: i32,
+) -> Result<usize, sqlx::Error> {
+    let pinned: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM threads WHERE board_id = $1 AND NOT archived AND pinned",
+    )
+    .bind(board.id)
+    .fetch_one(pool)
+    .await?;
+    let unpinned: Vec<i32> = sqlx::query_scalar(
+        r#"SELECT id FROM threads
+        WHERE board_id = $1 AND NOT archived AND NOT pinned AND id <> $2
+        ORDER BY last_updated DESC, id DESC"#,
+    )
+    .bind(board.id)
+    .bind(new_thread_id)
+    .fetch_all(pool)
+    .await?;
+
+    let thread_ids = over_limit(&unpinned, board.max_threads, pinned);
+    for &thread_id in thread_ids {
+        if board.archive_pruned {
+            sqlx::query("UPDATE threads SET archived = TRUE, locked = TRUE WHERE id = $1")
+                .bind(thread_id)
+                .execute(pool)
+                .await?;
+        } else {
+            delete_thread(pool, thread_id).await?;
+        }
+    }
+    Ok(thread_ids.len())
+}
+
+// The threads of `unpinned` (most recently bumped first, without the new thread) that
+// do not fit in `max_threads` once the pinned threads and the new thread are counted.
+// With the board full of pinned threads that is all of them.
+fn over_limit(unpinned: &[i32], max_threads: i32, pinned: i64) -> &[i32] {
+    let room = usize::try_from(i64::from(max_threads) - pinned - 1).unwrap_or(0);
+    &unpinned[room.min(unpinned.len())..]
+}
+
+/// Result of a garbage collection pass.
+pub struct GcReport {
+    pub orphans: Vec<PathBuf>,
+    pub bytes: u64,
+    pub removed: usize,
+}
+
+/// Finds files in the upload and thumbnail directories that no post references.
+/// With `dry_run` the files are only reported; otherwise they are deleted.
+pub async fn collect_garbage(pool: &Pool<Postgres>, dry_run: bool) -> Result<GcReport, sqlx::Error> {
+    let urls: Vec<String> = sqlx::query_scalar(
+        r#"SELECT media_url FROM threads WHERE media_url IS NOT NULL
+        UNION SELECT thumb_url FROM threads WHERE thumb_url IS NOT NULL
+        UNION SELECT media_url FROM replies WHERE media_url IS NOT NULL
+        UNION SELECT thumb_url FROM replies WHERE thumb_url IS NOT NULL"#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let referenced: HashSet<PathBuf> = urls
+        .iter()
+        .filter_map(|url| upload::media_path(url))
+        .collect();
+
+    let mut report = GcReport {
+        orphans: Vec::new(),
+        bytes: 0,
+        removed: 0,
+    };
+    let now = SystemTime::now();
+
+    let paths = &config::get().paths;
+    for dir in [&paths.image_uploads, &paths.video_uploads, &paths.image_thumbs] {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::warn!("Cannot scan {}: {}", dir.display(), e);
+                continue;
+            }
+        };
+
+        for entry in entries.flatten() {
+            let metadata = match entry.metadata() {
+                Ok(metadata) if metadata.is_file() => metadata,
+                _ => continue,
+            };
+            let path = dir.join(entry.file_name());
+            if referenced.contains(&path) {
+                continue;
+            }
+
+            let age = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .unwrap_or_default();
+            if age < GC_MIN_AGE {
+                continue;
+            }
+
+            report.bytes += metadata.len();
+            if !dry_run {
+                match std::fs::remove_file(&path) {
+                    Ok(()) => report.removed += 1,
+                    Err(e) => log::warn!("Failed to remove {}: {}", path.display(), e),
+                }
+            }
+            report.orphans.push(path);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Runs the garbage collector periodically for the lifetime of the server.
+pub fn spawn_gc_task(pool: Pool<Postgres>) {
+    actix_web::rt::spawn(async move {
+        let period = Duration::from_secs(config::get().maintenance.gc_interval);
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            match collect_garbage(&pool, false).await {
+                Ok(report) if report.removed > 0 => log::info!(
+                    "Media GC removed {} orphaned files ({} bytes)",
+                    report.removed,
+                    report.bytes
+                ),
+                Ok(_) => {}
+                Err(e) => log::error!("Media GC failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boards_under_their_limit_are_left_alone() {
+        assert_eq!(over_limit(&[], 10, 0), &[] as &[i32]);
+        assert_eq!(over_limit(&[9, 8, 7], 10, 0), &[] as &[i32]);
+        // Three older threads and the new one fill the board exactly
+        assert_eq!(over_limit(&[9, 8, 7], 4, 0), &[] as &[i32]);
+    }
+
+    #[test]
+    fn least_recently_bumped_threads_are_pruned_first() {
+        assert_eq!(over_limit(&[9, 8, 7, 6], 3, 0), &[7, 6]);
+        assert_eq!(over_limit(&[9, 8, 7, 6], 4, 1), &[7, 6]);
+    }
+
+    #[test]
+    fn boards_full_of_pinned_threads_keep_only_the_new_thread() {
+        assert_eq!(over_limit(&[9, 8, 7], 3, 2), &[9, 8, 7]);
+        assert_eq!(over_limit(&[9, 8, 7], 3, 3), &[9, 8, 7]);
+        assert_eq!(over_limit(&[9, 8, 7], 3, 5), &[9, 8, 7]);
+        assert_eq!(over_limit(&[9], 1, 0), &[9]);
+    }
+}
@@ -0,0 +1,307 @@
+// src/metadata.rs
+//
+// Reads and removes the metadata that cameras and editors embed in images:
+// EXIF (GPS position, camera serial numbers, orientation), XMP and comments.
+// Byte layouts follow the same container walks as sniff.rs, which has already
+// checked that the file holds together by the time anything here runs.
+
+use crate::sniff::{gif_color_table_len, gif_skip_sub_blocks, MediaKind};
+use image::DynamicImage;
+
+/// EXIF tag holding the orientation the camera was held in.
+const ORIENTATION_TAG: u16 = 0x0112;
+
+/// GIF application extensions that control animation and are kept when stripping.
+const GIF_LOOP_EXTENSIONS: [&[u8; 11]; 2] = [b"NETSCAPE2.0", b"ANIMEXTS1.0"];
+
+/// VP8X flag bits announcing EXIF and XMP chunks.
+const VP8X_EXIF_FLAG: u8 = 0x08;
+const VP8X_XMP_FLAG: u8 = 0x04;
+
+/// Reads the EXIF orientation (1 to 8) of an image, if it has one.
+pub fn exif_orientation(kind: MediaKind, data: &[u8]) -> Option<u16> {
+    let tiff = match kind {
+        MediaKind::Jpeg => jpeg_exif(data)?,
+        MediaKind::Png => png_chunk(data, b"eXIf")?,
+        MediaKind::Webp => {
+            let exif = webp_chunks(data)?.find(|(fourcc, _)| fourcc == b"EXIF")?.1;
+            // Some writers keep the JPEG-style prefix in the chunk
+            exif.strip_prefix(b"Exif\0\0").unwrap_or(exif)
+        }
+        MediaKind::Gif | MediaKind::Mp4 => return None,
+    };
+    tiff_orientation(tiff).filter(|o| (1..=8).contains(o))
+}
+
+/// Rotates and flips a decoded image so it displays upright without its EXIF orientation.
+pub fn apply_orientation(img: DynamicImage, orientation: Option<u16>) -> DynamicImage {
+    match orientation {
+        Some(2) => img.fliph(),
+        Some(3) => img.rotate180(),
+        Some(4) => img.flipv(),
+        Some(5) => img.rotate90().fliph(),
+        Some(6) => img.rotate90(),
+        Some(7) => img.rotate270().fliph(),
+        Some(8) => img.rotate270(),
+        _ => img,
+    }
+}
+
+// Payload of the APP1 segment that starts with the EXIF signature, searched for
+// among the segments before the first scan
+fn jpeg_exif(data: &[u8]) -> Option<&[u8]> {
+    let mut pos = 2;
+    loop {
+        while *data.get(pos)? == 0xFF {
+            pos += 1;
+        }
+        let marker = *data.get(pos)?;
+        pos += 1;
+        if matches!(marker, 0xD9 | 0xDA) {
+            return None;
+        }
+        if matches!(marker, 0x01 | 0xD0..=0xD7) {
+            continue;
+        }
+        let len = usize::from(u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?));
+        let payload = data.get(pos + 2..pos + len)?;
+        if marker == 0xE1 {
+            if let Some(tiff) = payload.strip_prefix(b"Exif\0\0") {
+                return Some(tiff);
+            }
+        }
+        pos += len;
+    }
+}
+
+fn png_chunk<'a>(data: &'a [u8], wanted: &[u8; 4]) -> Option<&'a [u8]> {
+    let mut pos = 8;
+    loop {
+        let len = u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        let chunk_type = data.get(pos + 4..pos + 8)?;
+        let payload = data.get(pos + 8..(pos + 8).checked_add(len)?)?;
+        if chunk_type == wanted {
+            return Some(payload);
+        }
+        if chunk_type == b"IEND" {
+            return None;
+        }
+        pos += len + 12;
+    }
+}
+
+// Iterates over the (fourcc, payload) chunks inside a WEBP's RIFF container
+fn webp_chunks(data: &[u8]) -> Option<impl Iterator<Item = (&[u8], &[u8])>> {
+    let mut rest = data.get(12..)?;
+    Some(std::iter::from_fn(move || {
+        let fourcc = rest.get(..4)?;
+        let len = u32::from_le_bytes(rest.get(4..8)?.try_into().ok()?) as usize;
+        let payload = rest.get(8..8usize.checked_add(len)?)?;
+        // Chunks are padded to an even length
+        rest = rest.get(8 + len + (len & 1)..).unwrap_or_default();
+        Some((fourcc, payload))
+    }))
+}
+
+fn tiff_orientation(tiff: &[u8]) -> Option<u16> {
+    let big_endian = match tiff.get(..4)? {
+        b"MM\0*" => true,
+        b"II*\0" => false,
+        _ => return None,
+    };
+    let u16_at = |pos: usize| -> Option<u16> {
+        let bytes: [u8; 2] = tiff.get(pos..pos + 2)?.try_into().ok()?;
+        Some(if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+    };
+    let u32_at = |pos: usize| -> Option<u32> {
+        let bytes: [u8; 4] = tiff.get(pos..pos + 4)?.try_into().ok()?;
+        Some(if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    };
+
+    // Entries of the first IFD are 12 bytes: tag, type, count, then the value itself
+    let ifd = u32_at(4)? as usize;
+    let entries = usize::from(u16_at(ifd)?);
+    (0..entries)
+        .map(|i| ifd + 2 + i * 12)
+        .find(|&entry| u16_at(entry) == Some(ORIENTATION_TAG))
+        .and_then(|entry| u16_at(entry + 8))
+}
+
+/// Copies a GIF without its comments and application extensions (where XMP lives),
+/// keeping frames, timing and the loop count so animations still play.
+pub fn strip_gif(data: &[u8]) -> Option<Vec<u8>> {
+    let flags = *data.get(10)?;
+    let mut pos = 13 + gif_color_table_len(flags);
+    let mut out = data.get(..pos)?.to_vec();
+    loop {
+        let start = pos;
+        match *data.get(pos)? {
+            0x3B => {
+                out.push(0x3B);
+                return Some(out);
+            }
+            0x21 => {
+                let label = *data.get(pos + 1)?;
+                pos = gif_skip_sub_blocks(data, pos + 2)?;
+                let keep = match label {
+                    0xFE => false,
+                    0xFF => data
+                        .get(start + 3..start + 14)
+                        .is_some_and(|id| GIF_LOOP_EXTENSIONS.iter().any(|known| &known[..] == id)),
+                    _ => true,
+                };
+                if keep {
+                    out.extend_from_slice(&data[start..pos]);
+                }
+            }
+            0x2C => {
+                let flags = *data.get(pos + 9)?;
+                pos = gif_skip_sub_blocks(data, pos + 10 + gif_color_table_len(flags) + 1)?;
+                out.extend_from_slice(&data[start..pos]);
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// Copies a WEBP without its EXIF and XMP chunks, leaving the image data (and any
+/// animation) untouched. There is no WEBP encoder to write rotated pixels with, so a
+/// non-default orientation is kept in a new EXIF chunk that holds nothing else.
+pub fn strip_webp(data: &[u8], orientation: Option<u16>) -> Option<Vec<u8>> {
+    let orientation_exif = orientation.filter(|&o| o != 1).map(orientation_only_exif);
+    let mut body = b"WEBP".to_vec();
+    for (fourcc, payload) in webp_chunks(data)? {
+        let payload = match (fourcc, &orientation_exif) {
+            (b"EXIF", Some(exif)) => exif.as_slice(),
+            (b"EXIF", None) | (b"XMP ", _) => continue,
+            _ => payload,
+        };
+        body.extend_from_slice(fourcc);
+        body.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        let start = body.len();
+        body.extend_from_slice(payload);
+        if fourcc == b"VP8X" {
+            // The extended header announces which optional chunks follow
+            if let Some(flags) = body.get_mut(start) {
+                *flags &= !VP8X_XMP_FLAG;
+                if orientation_exif.is_none() {
+                    *flags &= !VP8X_EXIF_FLAG;
+                }
+            }
+        }
+        if payload.len() % 2 == 1 {
+            body.push(0);
+        }
+    }
+
+    let mut out = b"RIFF".to_vec();
+    out.extend_from_slice(&u32::try_from(body.len()).ok()?.to_le_bytes());
+    out.extend_from_slice(&body);
+    Some(out)
+}
+
+// Little-endian TIFF header and a single IFD with one entry: the orientation
+fn orientation_only_exif(orientation: u16) -> Vec<u8> {
+    let mut tiff = b"II*\0".to_vec();
+    tiff.extend_from_slice(&8u32.to_le_bytes());
+    tiff.extend_from_slice(&1u16.to_le_bytes());
+    tiff.extend_from_slice(&ORIENTATION_TAG.to_le_bytes());
+    // Type SHORT, count 1, value padded to four bytes
+    tiff.extend_from_slice(&3u16.to_le_bytes());
+    tiff.extend_from_slice(&1u32.to_le_bytes());
+    tiff.extend_from_slice(&orientation.to_le_bytes());
+    tiff.extend_from_slice(&[0, 0]);
+    // No further IFDs
+    tiff.extend_from_slice(&0u32.to_le_bytes());
+    tiff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sniff::image_len;
+
+    fn webp_chunk(fourcc: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut chunk = fourcc.to_vec();
+        chunk.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(payload);
+        if payload.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    // An extended WEBP carrying camera EXIF (with an orientation) and XMP
+    fn webp_with_metadata(orientation: u16) -> Vec<u8> {
+        let mut exif = orientation_only_exif(orientation);
+        exif.extend_from_slice(b"Canon EOS serial 1234567 GPS 51.5N 0.1W");
+        let mut body = b"WEBP".to_vec();
+        let flags = VP8X_EXIF_FLAG | VP8X_XMP_FLAG;
+        body.extend(webp_chunk(b"VP8X", &[flags, 0, 0, 0, 15, 0, 0, 15, 0, 0]));
+        body.extend(webp_chunk(b"VP8L", &[0x2F, 0, 0, 0, 0]));
+        body.extend(webp_chunk(b"EXIF", &exif));
+        body.extend(webp_chunk(b"XMP ", b"<x:xmpmeta>author</x:xmpmeta>"));
+        let mut data = b"RIFF".to_vec();
+        data.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        data.extend(body);
+        data
+    }
+
+    fn fourccs(data: &[u8]) -> Vec<&[u8]> {
+        webp_chunks(data).unwrap().map(|(fourcc, _)| fourcc).collect()
+    }
+
+    #[test]
+    fn orientation_is_read_from_webp_exif() {
+        let data = webp_with_metadata(6);
+        assert_eq!(exif_orientation(MediaKind::Webp, &data), Some(6));
+    }
+
+    #[test]
+    fn strip_webp_keeps_an_orientation_only_exif() {
+        let stripped = strip_webp(&webp_with_metadata(6), Some(6)).unwrap();
+        assert_eq!(fourccs(&stripped), [&b"VP8X"[..], b"VP8L", b"EXIF"]);
+
+        let exif = webp_chunks(&stripped)
+            .unwrap()
+            .find(|(fourcc, _)| fourcc == b"EXIF")
+            .unwrap()
+            .1;
+        assert_eq!(exif, orientation_only_exif(6));
+        assert_eq!(exif_orientation(MediaKind::Webp, &stripped), Some(6));
+
+        let flags = stripped[20];
+        assert_eq!(flags & VP8X_EXIF_FLAG, VP8X_EXIF_FLAG);
+        assert_eq!(flags & VP8X_XMP_FLAG, 0);
+        assert_eq!(image_len(MediaKind::Webp, &stripped), Some(stripped.len()));
+    }
+
+    #[test]
+    fn strip_webp_drops_exif_without_a_rotation() {
+        for orientation in [None, Some(1)] {
+            let stripped = strip_webp(&webp_with_metadata(1), orientation).unwrap();
+            assert_eq!(fourccs(&stripped), [&b"VP8X"[..], b"VP8L"]);
+            assert_eq!(stripped[20] & (VP8X_EXIF_FLAG | VP8X_XMP_FLAG), 0);
+            assert_eq!(image_len(MediaKind::Webp, &stripped), Some(stripped.len()));
+        }
+    }
+
+    #[test]
+    fn strip_gif_drops_comments_and_keeps_the_loop_count() {
+        let mut data = b"GIF89a\x01\x00\x01\x00\x00\x00\x00".to_vec();
+        let netscape = b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00";
+        data.extend_from_slice(netscape);
+        data.extend_from_slice(b"\x21\xFE\x09a comment\x00");
+        data.extend_from_slice(b"\x21\xFF\x0BXMP DataXMP\x04<x:>\x00");
+        let frame = b"\x2C\x00\x00\x00\x00\x01\x00\x01\x00\x00\x02\x02\x44\x01\x00";
+        data.extend_from_slice(frame);
+        data.push(0x3B);
+
+        let stripped = strip_gif(&data).unwrap();
+        let mut expected = data[..13].to_vec();
+        expected.extend_from_slice(netscape);
+        expected.extend_from_slice(frame);
+        expected.push(0x3B);
+        assert_eq!(stripped, expected);
+    }
+}
@@ -0,0 +1,93 @@
+// src/poster.rs
+
+use crate::config;
+use crate::tripcode::{Signature, Tripcoder};
+use actix_web::{Error, HttpRequest};
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use std::net::{IpAddr, SocketAddr};
+
+/// The client behind a post: its address, used for IP range bans, and the salted hash
+/// that is stored with the post in place of the address.
+pub struct Poster {
+    pub ip: IpAddr,
+    pub hash: String,
+}
+
+/// Derives poster identities: the hash of the client address, and the name and
+/// tripcode a poster signs with. Shared with handlers as `web::Data`.
+pub struct PosterIdentifier {
+    salt: Vec<u8>,
+    trust_proxy_headers: bool,
+    tripcoder: Tripcoder,
+}
+
+impl PosterIdentifier {
+    /// Set up from `server.trust_proxy_headers`, `POSTER_HASH_SALT` and `TRIPCODE_SECRET`.
+    /// Without a salt a random one is used, so hashes (and hash bans) only hold until restart.
+    pub fn from_config() -> Self {
+        let config = config::get();
+        let salt = match config.secrets.poster_hash_salt.as_str() {
+            "" => {
+                log::warn!(
+                    "POSTER_HASH_SALT is not set; using a random salt. Poster hashes and \
+                     hash bans will not match across restarts."
+                );
+                let mut salt = vec![0u8; 32];
+                OsRng.fill_bytes(&mut salt);
+                salt
+            }
+            salt => salt.as_bytes().to_vec(),
+        };
+
+        PosterIdentifier {
+            salt,
+            trust_proxy_headers: config.server.trust_proxy_headers,
+            tripcoder: Tripcoder::new(&config.secrets.tripcode_secret),
+        }
+    }
+
+    /// Identifies the client of a request. The `Forwarded`/`X-Forwarded-For` headers are
+    /// only honoured with `server.trust_proxy_headers`, since clients can set them freely.
+    pub fn identify(&self, req: &HttpRequest) -> Result<Poster, Error> {
+        let ip = if self.trust_proxy_headers {
+            req.connection_info()
+                .realip_remote_addr()
+                .and_then(parse_addr)
+        } else {
+            req.peer_addr().map(|addr| addr.ip())
+        };
+        let ip = ip.ok_or_else(|| {
+            actix_web::error::ErrorBadRequest("Cannot determine the client address")
+        })?;
+
+        Ok(Poster {
+            ip,
+            hash: self.hash(&ip),
+        })
+    }
+
+    /// Reads the name field of a post form; see `Tripcoder::sign`.
+    pub fn sign(&self, name_field: &str) -> Signature {
+        self.tripcoder.sign(name_field)
+    }
+
+    fn hash(&self, ip: &IpAddr) -> String {
+        let digest = Sha256::new()
+            .chain_update(&self.salt)
+            .chain_update(ip.to_string())
+            .finalize();
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+fn parse_addr(addr: &str) -> Option<IpAddr> {
+    addr.parse::<IpAddr>()
+        .ok()
+        .or_else(|| addr.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
+
+/// Shortened hash for display in the admin pages.
+pub fn short_hash(hash: &str) -> &str {
+    &hash[..hash.len().min(10)]
+}
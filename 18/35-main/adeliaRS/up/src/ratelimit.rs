@@ -0,0 +1,212 @@
+// src/ratelimit.rs
+
+use crate::board::Board;
+use crate::config;
+use crate::render_error_page;
+use actix_web::{http::header::RETRY_AFTER, HttpResponse};
+use sqlx::{Pool, Postgres};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Posts a bucket can hold. With one token a poster gets exactly one post per cooldown.
+const BUCKET_CAPACITY: f64 = 1.0;
+
+/// Failed admin logins allowed in a row from one IP before it has to wait.
+const LOGIN_ATTEMPTS: f64 = 5.0;
+
+/// Time for a throttled IP to regain one login attempt.
+const LOGIN_REFILL: Duration = Duration::from_secs(60);
+
+/// Buckets are swept once the map grows past this many entries.
+const SWEEP_THRESHOLD: usize = 10_000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PostKind {
+    Thread,
+    Reply,
+}
+
+impl PostKind {
+    fn cooldown(self, board: &Board) -> Duration {
+        let seconds = match self {
+            PostKind::Thread => board.thread_cooldown,
+            PostKind::Reply => board.reply_cooldown,
+        };
+        Duration::from_secs(seconds.max(0) as u64)
+    }
+
+    fn noun(self) -> &'static str {
+        match self {
+            PostKind::Thread => "thread",
+            PostKind::Reply => "reply",
+        }
+    }
+}
+
+/// What a bucket limits for its IP.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Action {
+    Post(i32, PostKind),
+    Login,
+}
+
+struct Bucket {
+    tokens: f64,
+    capacity: f64,
+    updated: Instant,
+    /// Time to regain one token; kept so idle buckets can be swept.
+    refill: Duration,
+}
+
+impl Bucket {
+    fn refilled(&self, now: Instant) -> f64 {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        (self.tokens + elapsed / self.refill.as_secs_f64()).min(self.capacity)
+    }
+}
+
+/// In-process token buckets keyed by client IP and what is limited: posts per board and
+/// kind, or admin logins. Shared with handlers as `web::Data`; limits reset when the
+/// server restarts.
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<(IpAddr, Action), Bucket>>,
+}
+
+impl RateLimiter {
+    /// Takes a token for a post by `ip` on `board`.
+    /// Returns how long to wait when the bucket is empty. A cooldown of 0 disables the limit.
+    pub fn acquire(&self, ip: IpAddr, board: &Board, kind: PostKind) -> Result<(), Duration> {
+        let refill = kind.cooldown(board);
+        if refill.is_zero() {
+            return Ok(());
+        }
+        self.take(ip, Action::Post(board.id, kind), BUCKET_CAPACITY, refill)
+    }
+
+    /// Takes a login attempt for `ip`. Called before the password is checked, so guesses
+    /// sent in parallel are counted too; `login_succeeded` gives the attempts back.
+    pub fn acquire_login(&self, ip: IpAddr) -> Result<(), Duration> {
+        self.take(ip, Action::Login, LOGIN_ATTEMPTS, LOGIN_REFILL)
+    }
+
+    /// Forgets the failed logins from `ip` once it signs in.
+    pub fn login_succeeded(&self, ip: IpAddr) {
+        self.buckets.lock().unwrap().remove(&(ip, Action::Login));
+    }
+
+    fn take(
+        &self,
+        ip: IpAddr,
+        action: Action,
+        capacity: f64,
+        refill: Duration,
+    ) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= SWEEP_THRESHOLD {
+            buckets.retain(|_, bucket| bucket.refilled(now) < bucket.capacity);
+        }
+
+        let bucket = buckets.entry((ip, action)).or_insert(Bucket {
+            tokens: capacity,
+            capacity,
+            updated: now,
+            refill,
+        });
+        // Pick up cooldown changes made since the bucket was created
+        bucket.tokens = bucket.refilled(now);
+        bucket.updated = now;
+        bucket.refill = refill;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(refill.mul_f64(1.0 - bucket.tokens))
+        }
+    }
+}
+
+/// Whether the poster with `poster_hash` already posted the same message text on `board`
+/// within the configured `limits.duplicate_window`. Other posters may repeat it freely.
+pub async fn is_duplicate(
+    pool: &Pool<Postgres>,
+    board: &Board,
+    poster_hash: &str,
+    message: &str,
+    now: i64,
+) -> Result<bool, sqlx::Error> {
+    let window = config::get().limits.duplicate_window;
+    if window == 0 {
+        return Ok(false);
+    }
+    sqlx::query_scalar(
+        r#"SELECT EXISTS (
+                SELECT 1 FROM threads
+                WHERE board_id = $2 AND poster_hash = $3 AND created_at > $4 AND message = $1
+            )
+            OR EXISTS (
+                SELECT 1 FROM replies r JOIN threads t ON t.id = r.thread_id
+                WHERE t.board_id = $2 AND r.poster_hash = $3 AND r.created_at > $4
+                  AND r.message = $1
+            )"#,
+    )
+    .bind(message)
+    .bind(board.id)
+    .bind(poster_hash)
+    .bind(now - window)
+    .fetch_one(pool)
+    .await
+}
+
+/// Page shown when a poster has to wait before posting again.
+pub fn rate_limited_response(kind: PostKind, wait: Duration) -> HttpResponse {
+    slow_down(wait, |seconds| {
+        format!(
+            "You are posting too fast. You can post another {} on this board in {} seconds.",
+            kind.noun(),
+            seconds
+        )
+    })
+}
+
+/// Page shown when an IP has used up its login attempts.
+pub fn login_limited_response(wait: Duration) -> HttpResponse {
+    slow_down(wait, |seconds| {
+        format!(
+            "Too many failed logins from your address. Try again in {} seconds.",
+            seconds
+        )
+    })
+}
+
+fn slow_down(wait: Duration, message: impl FnOnce(u64) -> String) -> HttpResponse {
+    // Round up so the client never retries a moment too early
+    let seconds = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+    HttpResponse::TooManyRequests()
+        .insert_header((RETRY_AFTER, seconds.to_string()))
+        .content_type("text/html")
+        .body(render_error_page("Slow Down", &message(seconds)))
+}
+
+pub fn duplicate_response() -> HttpResponse {
+    let window = config::get().limits.duplicate_window;
+    let period = match window {
+        60 => "minute".to_string(),
+        w if w % 60 == 0 => format!("{} minutes", w / 60),
+        w => format!("{} seconds", w),
+    };
+    HttpResponse::TooManyRequests()
+        .insert_header((RETRY_AFTER, window.to_string()))
+        .content_type("text/html")
+        .body(render_error_page(
+            "Duplicate Message",
+            &format!(
+                "You posted this exact message on this board in the last {}. Write something new or try again later.",
+                period
+            ),
+        ))
+}
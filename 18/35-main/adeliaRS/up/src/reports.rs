@@ -0,0 +1,143 @@
+// src/reports.rs
+
+use sqlx::{Pool, Postgres};
+
+/// The post a report is about.
+#[derive(Clone, Copy)]
+pub enum ReportTarget {
+    Thread(i32),
+    Reply(i32),
+}
+
+impl ReportTarget {
+    /// Parses the `{kind}/{id}` segments of report URLs.
+    pub fn parse(kind: &str, id: i32) -> Option<Self> {
+        match kind {
+            "thread" => Some(ReportTarget::Thread(id)),
+            "reply" => Some(ReportTarget::Reply(id)),
+            _ => None,
+        }
+    }
+
+    pub fn kind(self) -> &'static str {
+        match self {
+            ReportTarget::Thread(_) => "thread",
+            ReportTarget::Reply(_) => "reply",
+        }
+    }
+
+    pub fn id(self) -> i32 {
+        match self {
+            ReportTarget::Thread(id) | ReportTarget::Reply(id) => id,
+        }
+    }
+
+    /// Returns the thread the post appears in and that thread's board,
+    /// or `None` if the post does not exist.
+    pub async fn locate(self, pool: &Pool<Postgres>) -> Result<Option<(i32, i32)>, sqlx::Error> {
+        match self {
+            ReportTarget::Thread(id) => {
+                sqlx::query_as("SELECT id, board_id FROM threads WHERE id = $1")
+                    .bind(id)
+                    .fetch_optional(pool)
+                    .await
+            }
+            ReportTarget::Reply(id) => {
+                sqlx::query_as(
+                    r#"SELECT t.id, t.board_id FROM replies r
+                    JOIN threads t ON t.id = r.thread_id WHERE r.id = $1"#,
+                )
+                .bind(id)
+                .fetch_optional(pool)
+                .await
+            }
+        }
+    }
+}
+
+/// Records a report. Returns `false` if this reporter had already reported the post.
+pub async fn file(
+    pool: &Pool<Postgres>,
+    target: ReportTarget,
+    reason: &str,
+    reporter_hash: &str,
+    now: i64,
+) -> Result<bool, sqlx::Error> {
+    let (thread_id, reply_id) = match target {
+        ReportTarget::Thread(id) => (Some(id), None),
+        ReportTarget::Reply(id) => (None, Some(id)),
+    };
+    let result = sqlx::query(
+        r#"INSERT INTO reports (thread_id, reply_id, reason, reporter_hash, created_at)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT DO NOTHING"#,
+    )
+    .bind(thread_id)
+    .bind(reply_id)
+    .bind(reason)
+    .bind(reporter_hash)
+    .bind(now)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// A reported post in the admin queue, with all of its reports combined.
+#[derive(sqlx::FromRow)]
+pub struct QueueEntry {
+    thread_id: Option<i32>,
+    reply_id: Option<i32>,
+    /// Thread the post appears in, for linking to it.
+    pub parent_thread_id: i32,
+    pub message: String,
+    pub report_count: i64,
+    pub last_reported: i64,
+    pub reasons: Vec<String>,
+}
+
+impl QueueEntry {
+    pub fn target(&self) -> ReportTarget {
+        match (self.thread_id, self.reply_id) {
+            (Some(id), _) => ReportTarget::Thread(id),
+            (None, id) => ReportTarget::Reply(id.unwrap_or_default()),
+        }
+    }
+}
+
+/// Reported posts, most reported first.
+pub async fn queue(pool: &Pool<Postgres>) -> Result<Vec<QueueEntry>, sqlx::Error> {
+    sqlx::query_as(
+        r#"SELECT r.thread_id, r.reply_id,
+            COALESCE(t.id, rp.thread_id) AS parent_thread_id,
+            COALESCE(t.message, rp.message) AS message,
+            COUNT(*) AS report_count,
+            MAX(r.created_at) AS last_reported,
+            ARRAY_AGG(r.reason ORDER BY r.created_at) AS reasons
+        FROM reports r
+        LEFT JOIN threads t ON t.id = r.thread_id
+        LEFT JOIN replies rp ON rp.id = r.reply_id
+        GROUP BY r.thread_id, r.reply_id, t.id, t.message, rp.thread_id, rp.message
+        ORDER BY report_count DESC, last_reported DESC"#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Number of posts waiting in the queue.
+pub async fn open_count(pool: &Pool<Postgres>) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT COUNT(DISTINCT (COALESCE(thread_id, 0), COALESCE(reply_id, 0))) FROM reports",
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Clears every report about a post, leaving the post itself alone.
+pub async fn dismiss(pool: &Pool<Postgres>, target: ReportTarget) -> Result<(), sqlx::Error> {
+    let query = match target {
+        ReportTarget::Thread(_) => "DELETE FROM reports WHERE thread_id = $1",
+        ReportTarget::Reply(_) => "DELETE FROM reports WHERE reply_id = $1",
+    };
+    sqlx::query(query).bind(target.id()).execute(pool).await?;
+    Ok(())
+}
@@ -0,0 +1,288 @@
+// src/search.rs
+//
+// Full-text search across threads and replies, backed by the `search_vector`
+// columns and their GIN indexes.
+
+use crate::board::{Board, BoardRegistry};
+use crate::{board_not_found, clamp_page, escape_html, format_timestamp, render_error_page};
+use crate::{render_pagination, threads_per_page};
+use actix_web::{web, Error, HttpResponse};
+use serde::Deserialize;
+use sqlx::{Pool, Postgres};
+
+/// Longest search query accepted, in characters.
+const MAX_QUERY_LEN: usize = 200;
+
+// ts_headline wraps matches in these. They are swapped for <mark> tags only after the
+// snippet has been escaped, so posts cannot smuggle markup into the results.
+const HIGHLIGHT_START: char = '\u{2}';
+const HIGHLIGHT_STOP: char = '\u{3}';
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    q: Option<String>,
+    /// Board URI to search in; empty or missing searches every board.
+    board: Option<String>,
+    page: Option<i32>,
+}
+
+#[derive(sqlx::FromRow)]
+struct SearchHit {
+    thread_id: i32,
+    post_no: i32,
+    board_id: i32,
+    title: String,
+    is_reply: bool,
+    created_at: i64,
+    snippet: String,
+}
+
+// Percent-encodes a value for use in a query string
+fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            b' ' => "+".to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+// Escapes a ts_headline snippet and turns its match markers into <mark> tags.
+// Posts can contain the marker characters themselves, so tags are only emitted
+// when they keep the markup balanced.
+fn render_snippet(snippet: &str) -> String {
+    let mut html = String::new();
+    let mut text = String::new();
+    let mut open = false;
+
+    for c in snippet.chars() {
+        let tag = match c {
+            HIGHLIGHT_START if !open => "<mark>",
+            HIGHLIGHT_STOP if open => "</mark>",
+            HIGHLIGHT_START | HIGHLIGHT_STOP => "",
+            _ => {
+                text.push(c);
+                continue;
+            }
+        };
+        html.push_str(&escape_html(&text));
+        text.clear();
+        if !tag.is_empty() {
+            html.push_str(tag);
+            open = !open;
+        }
+    }
+    html.push_str(&escape_html(&text));
+    if open {
+        html.push_str("</mark>");
+    }
+    html
+}
+
+async fn count_hits(
+    pool: &Pool<Postgres>,
+    query: &str,
+    board_ids: &[i32],
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"WITH q AS (SELECT websearch_to_tsquery('english', $1) AS query)
+        SELECT
+            (SELECT COUNT(*) FROM threads t CROSS JOIN q
+             WHERE t.search_vector @@ q.query AND t.board_id = ANY($2))
+          + (SELECT COUNT(*) FROM replies r JOIN threads t ON t.id = r.thread_id CROSS JOIN q
+             WHERE r.search_vector @@ q.query AND t.board_id = ANY($2))"#,
+    )
+    .bind(query)
+    .bind(board_ids)
+    .fetch_one(pool)
+    .await
+}
+
+// One page of matching posts, best match first. Snippets are only built for the
+// rows on the page, since ts_headline has to re-parse each message.
+async fn find_hits(
+    pool: &Pool<Postgres>,
+    query: &str,
+    board_ids: &[i32],
+    offset: i32,
+) -> Result<Vec<SearchHit>, sqlx::Error> {
+    let headline_options = format!(
+        "StartSel={}, StopSel={}, MaxWords=35, MinWords=15, MaxFragments=2, FragmentDelimiter=\" ... \"",
+        HIGHLIGHT_START, HIGHLIGHT_STOP
+    );
+
+    sqlx::query_as(
+        r#"WITH q AS (SELECT websearch_to_tsquery('english', $1) AS query),
+        hits AS (
+            SELECT t.id AS thread_id, t.post_no, t.board_id, t.title, t.message, t.created_at,
+                FALSE AS is_reply, ts_rank(t.search_vector, q.query) AS rank
+            FROM threads t CROSS JOIN q
+            WHERE t.search_vector @@ q.query AND t.board_id = ANY($2)
+            UNION ALL
+            SELECT r.thread_id, r.post_no, t.board_id, t.title, r.message, r.created_at,
+                TRUE, ts_rank(r.search_vector, q.query)
+            FROM replies r JOIN threads t ON t.id = r.thread_id CROSS JOIN q
+            WHERE r.search_vector @@ q.query AND t.board_id = ANY($2)
+            ORDER BY rank DESC, created_at DESC
+            LIMIT $3 OFFSET $4
+        )
+        SELECT thread_id, post_no, board_id, title, is_reply, created_at,
+            ts_headline('english', message, q.query, $5) AS snippet
+        FROM hits CROSS JOIN q
+        ORDER BY rank DESC, created_at DESC"#,
+    )
+    .bind(query)
+    .bind(board_ids)
+    .bind(threads_per_page())
+    .bind(offset)
+    .bind(headline_options)
+    .fetch_all(pool)
+    .await
+}
+
+fn render_hit(hit: &SearchHit, board: Option<&Board>) -> String {
+    let board_label = board
+        .map(|b| format!("/{}/", escape_html(&b.uri)))
+        .unwrap_or_default();
+    let kind = if hit.is_reply { "Reply in" } else { "Thread" };
+    format!(
+        r#"<div class="post search-result">
+    <div class="post-header">
+        {} {} <a href="/thread/{}#p{}">{}</a> <a class="post-no" href="/thread/{}#p{}">No. {}</a> <span class="post-date">{}</span>
+    </div>
+    <div class="message">{}</div>
+</div>"#,
+        board_label,
+        kind,
+        hit.thread_id,
+        hit.post_no,
+        escape_html(&hit.title),
+        hit.thread_id,
+        hit.post_no,
+        hit.post_no,
+        format_timestamp(hit.created_at),
+        render_snippet(&hit.snippet)
+    )
+}
+
+fn render_board_options(boards: &[Board], selected: Option<&Board>) -> String {
+    let mut options = r#"<option value="">All boards</option>"#.to_string();
+    for board in boards {
+        let is_selected = selected.is_some_and(|s| s.id == board.id);
+        options.push_str(&format!(
+            r#"<option value="{}"{}>/{}/ - {}</option>"#,
+            escape_html(&board.uri),
+            if is_selected { " selected" } else { "" },
+            escape_html(&board.uri),
+            escape_html(&board.name)
+        ));
+    }
+    options
+}
+
+// Search page: /search?q=&board=&page=
+pub async fn search(
+    pool: web::Data<Pool<Postgres>>,
+    registry: web::Data<BoardRegistry>,
+    query: web::Query<SearchQuery>,
+) -> Result<HttpResponse, Error> {
+    let query = query.into_inner();
+    let q = query.q.as_deref().unwrap_or("").trim();
+    if q.chars().count() > MAX_QUERY_LEN {
+        return Ok(HttpResponse::BadRequest().content_type("text/html").body(
+            render_error_page(
+                "Bad Request",
+                &format!("Search queries can be at most {} characters.", MAX_QUERY_LEN),
+            ),
+        ));
+    }
+
+    let boards = registry.live();
+    let board = match query.board.as_deref().filter(|uri| !uri.is_empty()) {
+        Some(uri) => match registry.get_by_uri(uri) {
+            Some(board) => Some(board),
+            None => return Ok(board_not_found()),
+        },
+        None => None,
+    };
+    let board_ids: Vec<i32> = match &board {
+        Some(board) => vec![board.id],
+        None => boards.iter().map(|b| b.id).collect(),
+    };
+
+    let results_html = if q.is_empty() {
+        String::new()
+    } else {
+        let total_hits = count_hits(pool.get_ref(), q, &board_ids)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        let (page_number, total_pages) = clamp_page(query.page, total_hits);
+        let offset = (page_number - 1) * threads_per_page();
+
+        let hits = find_hits(pool.get_ref(), q, &board_ids, offset)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+
+        if hits.is_empty() {
+            "<p>No posts matched your search.</p>".to_string()
+        } else {
+            let hits_html = hits
+                .iter()
+                .map(|hit| render_hit(hit, boards.iter().find(|b| b.id == hit.board_id)))
+                .collect::<Vec<String>>()
+                .join("<hr>");
+            let base = format!(
+                "/search?q={}&amp;board={}&amp;",
+                encode_query_value(q),
+                board
+                    .as_ref()
+                    .map(|b| encode_query_value(&b.uri))
+                    .unwrap_or_default()
+            );
+            format!(
+                r#"<p>{} {} found.</p>
+    <div class="postlists">{}</div>
+    {}"#,
+                total_hits,
+                if total_hits == 1 { "post" } else { "posts" },
+                hits_html,
+                render_pagination(&base, page_number, total_pages)
+            )
+        }
+    };
+
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Search</title>
+    <link rel="stylesheet" href="/static/style.css">
+</head>
+<body>
+    <div class="navigation-board">
+        <hr class="hr-green">
+        <a href="/">[Home]</a>
+    </div>
+    <h2>Search</h2>
+    <form class="search-form" action="/search" method="get">
+        <input type="search" name="q" value="{}" maxlength="{}" placeholder="Search posts" required>
+        <select name="board">{}</select>
+        <input type="submit" value="Search">
+    </form>
+    <hr>
+    {}
+</body>
+</html>"#,
+        escape_html(q),
+        MAX_QUERY_LEN,
+        render_board_options(&boards, board.as_ref()),
+        results_html
+    );
+
+    Ok(HttpResponse::Ok().content_type("text/html").body(html))
+}
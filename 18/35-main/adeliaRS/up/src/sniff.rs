@@ -0,0 +1,482 @@
+// src/sniff.rs
+//
+// Identifies uploads by their contents instead of their file names, and finds
+// where each container really ends so anything appended after it can be cut off.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// Bytes needed from the start of a file to identify it.
+pub const SNIFF_LEN: usize = 12;
+
+/// Brands in an MP4 `ftyp` box that mark the file as something browsers can play.
+/// QuickTime (`qt  `) and other ISO media flavours are refused.
+const MP4_BRANDS: [&[u8; 4]; 11] = [
+    b"isom", b"iso2", b"iso3", b"iso4", b"iso5", b"iso6", b"mp41", b"mp42", b"avc1", b"dash",
+    b"M4V ",
+];
+
+/// Top-level MP4 boxes that are kept. Boxes of any other type, including `meta` and
+/// `uuid` boxes that carry tags and XMP, are blanked.
+const MP4_BOXES: [&[u8; 4]; 11] = [
+    b"ftyp", b"moov", b"mdat", b"pdin", b"moof", b"mfra", b"styp", b"sidx", b"ssix", b"prft",
+    b"emsg",
+];
+
+/// Boxes inside `moov` and its tracks that hold titles, locations, tags and other
+/// metadata rather than anything needed for playback.
+const MP4_METADATA_BOXES: [&[u8; 4]; 2] = [b"udta", b"meta"];
+
+/// Largest `ftyp` box read when checking brands; real ones are a few dozen bytes.
+const MAX_FTYP_LEN: u64 = 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Jpeg,
+    Png,
+    Gif,
+    Webp,
+    Mp4,
+}
+
+impl MediaKind {
+    /// Identifies a file from its first `SNIFF_LEN` bytes.
+    pub fn detect(head: &[u8]) -> Option<MediaKind> {
+        if head.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(MediaKind::Jpeg)
+        } else if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(MediaKind::Png)
+        } else if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+            Some(MediaKind::Gif)
+        } else if head.len() >= 12 && &head[..4] == b"RIFF" && &head[8..12] == b"WEBP" {
+            Some(MediaKind::Webp)
+        } else if head.len() >= 8 && &head[4..8] == b"ftyp" {
+            Some(MediaKind::Mp4)
+        } else {
+            None
+        }
+    }
+
+    pub fn mime(self) -> &'static str {
+        match self {
+            MediaKind::Jpeg => "image/jpeg",
+            MediaKind::Png => "image/png",
+            MediaKind::Gif => "image/gif",
+            MediaKind::Webp => "image/webp",
+            MediaKind::Mp4 => "video/mp4",
+        }
+    }
+
+    /// Extension the file is stored under, which is also what it is served as.
+    pub fn extension(self) -> &'static str {
+        match self {
+            MediaKind::Jpeg => "jpg",
+            MediaKind::Png => "png",
+            MediaKind::Gif => "gif",
+            MediaKind::Webp => "webp",
+            MediaKind::Mp4 => "mp4",
+        }
+    }
+
+    /// Decoder for image kinds; `None` for video.
+    pub fn image_format(self) -> Option<image::ImageFormat> {
+        match self {
+            MediaKind::Jpeg => Some(image::ImageFormat::Jpeg),
+            MediaKind::Png => Some(image::ImageFormat::Png),
+            MediaKind::Gif => Some(image::ImageFormat::Gif),
+            MediaKind::Webp => Some(image::ImageFormat::WebP),
+            MediaKind::Mp4 => None,
+        }
+    }
+
+    pub fn is_image(self) -> bool {
+        self != MediaKind::Mp4
+    }
+}
+
+/// Length of the image at the start of `data`, up to and including its end marker.
+/// Returns `None` when the structure is broken or the file stops early.
+pub fn image_len(kind: MediaKind, data: &[u8]) -> Option<usize> {
+    match kind {
+        MediaKind::Jpeg => jpeg_len(data),
+        MediaKind::Png => png_len(data),
+        MediaKind::Gif => gif_len(data),
+        MediaKind::Webp => webp_len(data),
+        MediaKind::Mp4 => None,
+    }
+}
+
+fn read_u16_be(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn read_u32_be(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+// Walks the marker segments up to EOI, skipping over the entropy-coded data after
+// each start-of-scan (progressive images have several)
+fn jpeg_len(data: &[u8]) -> Option<usize> {
+    let mut pos = 2;
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        // Any number of 0xFF fill bytes may come before the marker code
+        while *data.get(pos)? == 0xFF {
+            pos += 1;
+        }
+        let marker = data[pos];
+        pos += 1;
+
+        match marker {
+            0xD9 => return Some(pos),
+            0x01 | 0xD0..=0xD7 => continue,
+            _ => {
+                let len = usize::from(read_u16_be(data, pos)?);
+                if len < 2 || pos + len > data.len() {
+                    return None;
+                }
+                pos += len;
+            }
+        }
+
+        if marker == 0xDA {
+            // Scan data ends at the first marker that is neither a stuffed zero nor a restart
+            loop {
+                pos += data.get(pos..)?.iter().position(|&b| b == 0xFF)?;
+                match *data.get(pos + 1)? {
+                    0x00 | 0xD0..=0xD7 => pos += 2,
+                    _ => break,
+                }
+            }
+        }
+    }
+}
+
+fn png_len(data: &[u8]) -> Option<usize> {
+    let mut pos = 8;
+    loop {
+        let len = read_u32_be(data, pos)? as usize;
+        let chunk_type = data.get(pos + 4..pos + 8)?;
+        // Length, type, data and CRC
+        pos = pos.checked_add(len)?.checked_add(12)?;
+        if pos > data.len() {
+            return None;
+        }
+        if chunk_type == b"IEND" {
+            return Some(pos);
+        }
+    }
+}
+
+/// Bytes taken by a GIF colour table, from the flags byte of the descriptor it follows.
+pub fn gif_color_table_len(flags: u8) -> usize {
+    if flags & 0x80 != 0 {
+        3 << ((flags & 0x07) + 1)
+    } else {
+        0
+    }
+}
+
+/// Skips a chain of GIF data sub-blocks, returning the position after the zero-length terminator.
+pub fn gif_skip_sub_blocks(data: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let size = usize::from(*data.get(pos)?);
+        pos += 1 + size;
+        if size == 0 {
+            return Some(pos);
+        }
+    }
+}
+
+fn gif_len(data: &[u8]) -> Option<usize> {
+    // Header and logical screen descriptor, then the global colour table
+    let mut pos = 13 + gif_color_table_len(*data.get(10)?);
+    loop {
+        match *data.get(pos)? {
+            0x3B => return Some(pos + 1),
+            // Extension: introducer, label, then sub-blocks
+            0x21 => pos = gif_skip_sub_blocks(data, pos + 2)?,
+            // Image: descriptor, local colour table, LZW code size, then sub-blocks
+            0x2C => {
+                let flags = *data.get(pos + 9)?;
+                pos = gif_skip_sub_blocks(data, pos + 10 + gif_color_table_len(flags) + 1)?;
+            }
+            _ => return None,
+        }
+    }
+}
+
+fn webp_len(data: &[u8]) -> Option<usize> {
+    let size = u32::from_le_bytes(data.get(4..8)?.try_into().ok()?) as usize;
+    let end = size.checked_add(8)?;
+    if size < 4 || end > data.len() {
+        return None;
+    }
+    Some(end)
+}
+
+fn has_mp4_brand(ftyp: &[u8]) -> bool {
+    // Major brand, minor version, then compatible brands
+    let major = ftyp.get(..4).into_iter();
+    let compatible = ftyp.get(8..).unwrap_or_default().chunks_exact(4);
+    major
+        .chain(compatible)
+        .any(|brand| MP4_BRANDS.iter().any(|known| &known[..] == brand))
+}
+
+/// Walks the top-level boxes of an MP4 file and returns where the last one ends,
+/// blanking every box that is not needed for playback along the way. The walk stops
+/// at the first box that is truncated or whose type is not printable, and the file is
+/// refused (`None`) unless it opened with an MP4 `ftyp` box and had both a `moov` and
+/// an `mdat` box before that point.
+pub fn scrub_mp4(file: &mut File) -> io::Result<Option<u64>> {
+    let file_len = file.metadata()?.len();
+    let mut pos: u64 = 0;
+    let mut has_moov = false;
+    let mut has_mdat = false;
+
+    while let Some((box_type, size, header_len)) = mp4_box_at(file, pos, file_len)? {
+        if pos == 0 {
+            if &box_type != b"ftyp" || size > MAX_FTYP_LEN {
+                return Ok(None);
+            }
+            let mut ftyp = vec![0u8; (size - header_len) as usize];
+            file.read_exact(&mut ftyp)?;
+            if !has_mp4_brand(&ftyp) {
+                return Ok(None);
+            }
+        }
+        if &box_type == b"moov" {
+            blank_mp4_metadata(file, pos + header_len, pos + size)?;
+        } else if !MP4_BOXES.contains(&&box_type) {
+            blank_mp4_box(file, pos, size, header_len)?;
+        }
+        has_moov |= &box_type == b"moov";
+        has_mdat |= &box_type == b"mdat";
+        pos += size;
+    }
+
+    Ok((has_moov && has_mdat).then_some(pos))
+}
+
+// Reads the header of the box at `pos`, leaving the file at the start of its contents.
+// Returns its type, total size and header size, or `None` if it does not fit before `end`.
+fn mp4_box_at(file: &mut File, pos: u64, end: u64) -> io::Result<Option<([u8; 4], u64, u64)>> {
+    if end - pos < 8 {
+        return Ok(None);
+    }
+    let mut header = [0u8; 8];
+    file.seek(SeekFrom::Start(pos))?;
+    file.read_exact(&mut header)?;
+    let box_type: [u8; 4] = header[4..].try_into().unwrap_or_default();
+
+    let size32 = u32::from_be_bytes(header[..4].try_into().unwrap_or_default());
+    let (size, header_len) = match size32 {
+        // Box runs to the end of its parent
+        0 => (end - pos, 8),
+        // 64-bit size follows the type
+        1 => {
+            if end - pos < 16 {
+                return Ok(None);
+            }
+            let mut large = [0u8; 8];
+            file.read_exact(&mut large)?;
+            (u64::from_be_bytes(large), 16)
+        }
+        size => (u64::from(size), 8),
+    };
+    let printable = box_type.iter().all(|&b| b.is_ascii_graphic() || b == b' ');
+    let fits = size >= header_len && size <= end - pos;
+    Ok((printable && fits).then_some((box_type, size, header_len)))
+}
+
+// Blanks the metadata boxes among the children of a `moov` or `trak` box, which lie
+// between `start` and `end`, and those of every track inside it
+fn blank_mp4_metadata(file: &mut File, start: u64, end: u64) -> io::Result<()> {
+    let mut pos = start;
+    while let Some((box_type, size, header_len)) = mp4_box_at(file, pos, end)? {
+        if MP4_METADATA_BOXES.contains(&&box_type) {
+            blank_mp4_box(file, pos, size, header_len)?;
+        } else if &box_type == b"trak" {
+            blank_mp4_metadata(file, pos + header_len, pos + size)?;
+        }
+        pos += size;
+    }
+    Ok(())
+}
+
+// Turns a box into a `free` box of the same size with its contents zeroed. Removing it
+// outright would move the media data that offsets in `moov` point at.
+fn blank_mp4_box(file: &mut File, pos: u64, size: u64, header_len: u64) -> io::Result<()> {
+    file.seek(SeekFrom::Start(pos + 4))?;
+    file.write_all(b"free")?;
+    file.seek(SeekFrom::Start(pos + header_len))?;
+    let zeros = [0u8; 8192];
+    let mut left = size - header_len;
+    while left > 0 {
+        let n = left.min(zeros.len() as u64) as usize;
+        file.write_all(&zeros[..n])?;
+        left -= n as u64;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageOutputFormat};
+    use std::io::{Cursor, Write};
+
+    const GARBAGE: &[u8] = b"PK\x03\x04 appended archive";
+
+    fn encoded(format: ImageOutputFormat) -> Vec<u8> {
+        let mut data = Vec::new();
+        DynamicImage::new_rgb8(16, 16)
+            .write_to(&mut Cursor::new(&mut data), format)
+            .unwrap();
+        data
+    }
+
+    // A lossless WEBP container; the VP8L payload is never decoded here
+    fn webp() -> Vec<u8> {
+        let mut data = b"RIFF".to_vec();
+        data.extend_from_slice(&18u32.to_le_bytes());
+        data.extend_from_slice(b"WEBPVP8L");
+        data.extend_from_slice(&5u32.to_le_bytes());
+        data.extend_from_slice(&[0x2F, 0, 0, 0, 0, 0]);
+        data
+    }
+
+    fn images() -> Vec<(MediaKind, Vec<u8>)> {
+        vec![
+            (MediaKind::Jpeg, encoded(ImageOutputFormat::Jpeg(90))),
+            (MediaKind::Png, encoded(ImageOutputFormat::Png)),
+            (MediaKind::Gif, encoded(ImageOutputFormat::Gif)),
+            (MediaKind::Webp, webp()),
+        ]
+    }
+
+    fn mp4_box(box_type: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut data = (8 + payload.len() as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(box_type);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    fn mp4(brand: &[u8; 4]) -> Vec<u8> {
+        let mut ftyp = brand.to_vec();
+        ftyp.extend_from_slice(&[0, 0, 2, 0]);
+        ftyp.extend_from_slice(brand);
+        let mut data = mp4_box(b"ftyp", &ftyp);
+        data.extend(mp4_box(b"moov", &[0; 16]));
+        data.extend(mp4_box(b"mdat", &[0; 32]));
+        data
+    }
+
+    // Runs `scrub_mp4` over `data` written to a temporary file, returning the length
+    // it found and the file as it was left
+    fn scrub_mp4_of(name: &str, data: &[u8]) -> (Option<u64>, Vec<u8>) {
+        let path = std::env::temp_dir()
+            .join(format!("chess_board_sniff_{}_{}", std::process::id(), name));
+        File::create(&path).unwrap().write_all(data).unwrap();
+        let mut file = File::options().read(true).write(true).open(&path).unwrap();
+        let len = scrub_mp4(&mut file).unwrap();
+        let scrubbed = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        (len, scrubbed)
+    }
+
+    fn mp4_len_of(name: &str, data: &[u8]) -> Option<u64> {
+        scrub_mp4_of(name, data).0
+    }
+
+    // An MP4 with `boxes` between its `ftyp` and `mdat` boxes
+    fn mp4_with(boxes: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = mp4_box(b"ftyp", b"isom\0\0\x02\0isom");
+        data.extend(boxes.concat());
+        data.extend(mp4_box(b"mdat", &[0; 32]));
+        data
+    }
+
+    #[test]
+    fn kinds_are_detected_from_contents() {
+        for (kind, data) in images() {
+            assert!(MediaKind::detect(&data[..SNIFF_LEN]) == Some(kind), "{}", kind.mime());
+        }
+        assert!(MediaKind::detect(&mp4(b"isom")[..SNIFF_LEN]) == Some(MediaKind::Mp4));
+        assert!(MediaKind::detect(b"<html><body>").is_none());
+    }
+
+    #[test]
+    fn whole_images_are_measured_exactly() {
+        for (kind, data) in images() {
+            assert_eq!(image_len(kind, &data), Some(data.len()), "{}", kind.mime());
+        }
+    }
+
+    #[test]
+    fn trailing_garbage_is_trimmed_from_images() {
+        for (kind, data) in images() {
+            let mut padded = data.clone();
+            padded.extend_from_slice(GARBAGE);
+            assert_eq!(image_len(kind, &padded), Some(data.len()), "{}", kind.mime());
+        }
+    }
+
+    #[test]
+    fn truncated_images_are_refused() {
+        for (kind, data) in images() {
+            for cut in [SNIFF_LEN, data.len() / 2, data.len() - 1] {
+                assert_eq!(image_len(kind, &data[..cut]), None, "{} cut at {}", kind.mime(), cut);
+            }
+        }
+    }
+
+    #[test]
+    fn trailing_garbage_is_trimmed_from_mp4() {
+        let data = mp4(b"isom");
+        let mut padded = data.clone();
+        padded.extend_from_slice(GARBAGE);
+        assert_eq!(mp4_len_of("padded.mp4", &padded), Some(data.len() as u64));
+    }
+
+    #[test]
+    fn truncated_mp4_is_refused() {
+        let data = mp4(b"mp42");
+        // Cutting into the mdat box leaves a file with no media data
+        assert_eq!(mp4_len_of("truncated.mp4", &data[..data.len() - 1]), None);
+    }
+
+    #[test]
+    fn quicktime_brand_is_refused() {
+        assert_eq!(mp4_len_of("quicktime.mov", &mp4(b"qt  ")), None);
+    }
+
+    #[test]
+    fn unknown_boxes_before_moov_are_blanked() {
+        let xmp = mp4_box(b"uuid", b"<x:xmpmeta>GPS</x:xmpmeta>");
+        let data = mp4_with(&[mp4_box(b"udta", b"camera serial"), xmp, mp4_box(b"moov", &[0; 16])]);
+        let (len, scrubbed) = scrub_mp4_of("unknown.mp4", &data);
+        assert_eq!(len, Some(data.len() as u64));
+        assert_eq!(scrubbed.len(), data.len());
+        let blanked = [mp4_box(b"free", &[0; 13]), mp4_box(b"free", &[0; 26])].concat();
+        assert_eq!(scrubbed, mp4_with(&[blanked, mp4_box(b"moov", &[0; 16])]));
+    }
+
+    #[test]
+    fn metadata_inside_moov_is_blanked() {
+        let mvhd = mp4_box(b"mvhd", &[7; 12]);
+        let tkhd = mp4_box(b"tkhd", &[9; 12]);
+        let moov = |udta: Vec<u8>| {
+            let trak = mp4_box(b"trak", &[tkhd.clone(), udta.clone()].concat());
+            mp4_box(b"moov", &[mvhd.clone(), udta, trak].concat())
+        };
+        let location = b"\xa9xyz+51.5-0.1";
+        let data = mp4_with(&[moov(mp4_box(b"udta", location))]);
+        let (len, scrubbed) = scrub_mp4_of("moov_udta.mp4", &data);
+        assert_eq!(len, Some(data.len() as u64));
+        assert_eq!(scrubbed, mp4_with(&[moov(mp4_box(b"free", &vec![0; location.len()]))]));
+    }
+}
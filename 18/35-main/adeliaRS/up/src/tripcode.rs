@@ -0,0 +1,189 @@
+// src/tripcode.rs
+//
+// Optional poster names with tripcodes. Typing `name#secret` in the name field shows
+// the name followed by a code derived from the secret, so a poster can prove that
+// posts come from the same person without an account. Only the name and the code
+// are stored; the secret never leaves this module.
+//
+// `name#secret` gives a regular tripcode (`!abc…`), an HMAC of the secret under a
+// fixed key that anyone can compute, so short secrets can be guessed offline.
+// `name##secret` gives a secure tripcode (`!!abc…`), keyed with `TRIPCODE_SECRET`
+// instead, so it can only be produced through this server.
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Shown for posts without a name.
+pub const DEFAULT_NAME: &str = "Anonymous";
+
+/// Characters in a tripcode after its `!` or `!!` marker.
+const TRIPCODE_LEN: usize = 10;
+
+/// Characters tripcodes are written with, six bits each.
+const TRIPCODE_ALPHABET: &[u8; 64] =
+    b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Keys regular tripcodes, so they are specific to this software but not to the server.
+const REGULAR_KEY: &[u8] = b"chess_board tripcode";
+
+/// What a post is signed with: the name as typed (without the secret) and its tripcode.
+pub struct Signature {
+    pub name: Option<String>,
+    pub tripcode: Option<String>,
+}
+
+/// Turns name fields into signatures. Held by `PosterIdentifier`.
+pub struct Tripcoder {
+    pepper: Vec<u8>,
+}
+
+impl Tripcoder {
+    /// Keys secure tripcodes with `secret`, the `TRIPCODE_SECRET`. Without one a random
+    /// secret is used, so secure tripcodes only stay the same until restart.
+    pub fn new(secret: &str) -> Self {
+        let pepper = match secret {
+            "" => {
+                log::warn!(
+                    "TRIPCODE_SECRET is not set; using a random secret. Secure tripcodes \
+                     will change on every restart."
+                );
+                let mut pepper = vec![0u8; 32];
+                OsRng.fill_bytes(&mut pepper);
+                pepper
+            }
+            secret => secret.as_bytes().to_vec(),
+        };
+        Tripcoder { pepper }
+    }
+
+    /// Splits a name field at its first `#` into the name and the tripcode secret.
+    /// Control characters, bidi overrides and `!` are dropped from the name so it cannot
+    /// imitate a tripcode or reorder the one shown after it, and a name left empty or
+    /// equal to the default counts as no name.
+    pub fn sign(&self, input: &str) -> Signature {
+        let (name, secret) = match input.split_once('#') {
+            Some((name, secret)) => (name, Some(secret)),
+            None => (input, None),
+        };
+
+        let name: String = name
+            .chars()
+            .filter(|&c| !c.is_control() && !is_bidi_control(c) && c != '!')
+            .collect();
+        let name = name.trim();
+        let name = (!name.is_empty() && name != DEFAULT_NAME).then(|| name.to_string());
+
+        let tripcode = match secret {
+            None | Some("") | Some("#") => None,
+            Some(secret) => Some(match secret.strip_prefix('#') {
+                Some(secret) => format!("!!{}", encode(&self.pepper, secret)),
+                None => format!("!{}", encode(REGULAR_KEY, secret)),
+            }),
+        };
+
+        Signature { name, tripcode }
+    }
+}
+
+// Marks and embeddings that change the direction of the text around them
+fn is_bidi_control(c: char) -> bool {
+    matches!(
+        c,
+        '\u{061C}' | '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}'
+    )
+}
+
+fn encode(key: &[u8], secret: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(secret.as_bytes());
+    let digest = mac.finalize().into_bytes();
+    let bits = u64::from_be_bytes(digest[..8].try_into().unwrap_or_default());
+    (0..TRIPCODE_LEN)
+        .map(|i| char::from(TRIPCODE_ALPHABET[(bits >> (58 - 6 * i)) as usize & 0x3F]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tripcoder(pepper: &str) -> Tripcoder {
+        Tripcoder {
+            pepper: pepper.as_bytes().to_vec(),
+        }
+    }
+
+    fn sign(input: &str) -> (Option<String>, Option<String>) {
+        let signature = tripcoder("pepper").sign(input);
+        (signature.name, signature.tripcode)
+    }
+
+    #[test]
+    fn names_without_a_secret_have_no_tripcode() {
+        assert_eq!(sign("Magnus"), (Some("Magnus".to_string()), None));
+        assert_eq!(sign("  Magnus  "), (Some("Magnus".to_string()), None));
+    }
+
+    #[test]
+    fn empty_and_default_names_count_as_no_name() {
+        for input in ["", "   ", DEFAULT_NAME, " Anonymous ", "\u{7}", "!!"] {
+            assert_eq!(sign(input), (None, None), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn names_cannot_imitate_a_tripcode() {
+        assert_eq!(sign("Magnus !P8nPXvqC17").0.as_deref(), Some("Magnus P8nPXvqC17"));
+        assert_eq!(sign("!!fake").0.as_deref(), Some("fake"));
+        assert_eq!(sign("Mag\u{202E}\nnus\u{2067}").0.as_deref(), Some("Magnus"));
+    }
+
+    #[test]
+    fn regular_tripcodes_are_stable_and_the_same_on_every_server() {
+        assert_eq!(
+            sign("Magnus#hunter2"),
+            (Some("Magnus".to_string()), Some("!P8nPXvqC17".to_string()))
+        );
+        assert_eq!(
+            tripcoder("another pepper").sign("#hunter2").tripcode.as_deref(),
+            Some("!P8nPXvqC17")
+        );
+        assert_ne!(sign("#hunter3").1, sign("#hunter2").1);
+    }
+
+    #[test]
+    fn secure_tripcodes_depend_on_the_server_secret() {
+        assert_eq!(
+            sign("Magnus##hunter2"),
+            (Some("Magnus".to_string()), Some("!!s5L7CRGggT".to_string()))
+        );
+        assert_ne!(
+            tripcoder("another pepper").sign("##hunter2").tripcode,
+            sign("##hunter2").1
+        );
+    }
+
+    #[test]
+    fn tripcodes_use_only_their_alphabet() {
+        for input in ["#a", "#é", "##a", "#hunter2#with#hashes"] {
+            let tripcode = sign(input).1.unwrap();
+            let code = tripcode.trim_start_matches('!');
+            assert_eq!(code.len(), TRIPCODE_LEN, "{:?}", tripcode);
+            assert!(code.bytes().all(|b| TRIPCODE_ALPHABET.contains(&b)), "{:?}", tripcode);
+        }
+    }
+
+    #[test]
+    fn only_the_first_hash_separates_the_secret() {
+        assert_eq!(sign("Magnus#a#b").1, sign("#a#b").1);
+        assert_ne!(sign("Magnus#a#b").1, sign("#a").1);
+    }
+
+    #[test]
+    fn empty_secrets_give_no_tripcode() {
+        assert_eq!(sign("Magnus#"), (Some("Magnus".to_string()), None));
+        assert_eq!(sign("Magnus##"), (Some("Magnus".to_string()), None));
+        assert_eq!(sign("#"), (None, None));
+    }
+}
@@ -0,0 +1,451 @@
+// src/upload.rs
+
+use crate::sniff::{self, MediaKind};
+use crate::{config, hashes, metadata};
+use actix_multipart::Field;
+use actix_web::web;
+use futures_util::stream::StreamExt;
+use image::DynamicImage;
+use mime_guess::mime;
+use sqlx::{Pool, Postgres};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// A media file that has been written to one of the upload directories.
+pub struct SavedMedia {
+    pub url: String,
+    pub media_type: String,
+    pub thumb_url: Option<String>,
+    /// Width and height of images as displayed, after applying their EXIF orientation.
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    /// Size of the stored file, in bytes.
+    pub size: i64,
+    /// SHA-256 of the upload as received, before metadata was stripped.
+    pub hash: String,
+    /// Perceptual hash of images, matched against the banned image list.
+    pub phash: Option<i64>,
+    /// The file belongs to an earlier post with the same contents, so it must be
+    /// left in place if this post is abandoned.
+    pub shared: bool,
+}
+
+pub enum UploadError {
+    /// The upload was refused; the message is safe to show to the poster.
+    Rejected(&'static str),
+    /// The file went over the size limit for its type, in bytes.
+    TooLarge(u64),
+    /// Kept `Send` so the work that produces it can run on the blocking thread pool.
+    Internal(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl From<std::io::Error> for UploadError {
+    fn from(e: std::io::Error) -> Self {
+        UploadError::Internal(e.into())
+    }
+}
+
+impl From<sqlx::Error> for UploadError {
+    fn from(e: sqlx::Error) -> Self {
+        UploadError::Internal(e.into())
+    }
+}
+
+impl From<actix_multipart::MultipartError> for UploadError {
+    fn from(e: actix_multipart::MultipartError) -> Self {
+        UploadError::Internal(e.into())
+    }
+}
+
+/// Formats a byte count for display, e.g. `8 MB` or `512 KB`.
+pub fn format_file_size(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = 1024 * KB;
+    if bytes >= MB && bytes.is_multiple_of(MB) {
+        format!("{} MB", bytes / MB)
+    } else if bytes >= MB {
+        format!("{:.1} MB", bytes as f64 / MB as f64)
+    } else if bytes >= KB {
+        format!("{} KB", bytes / KB)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+/// Reads a plain text multipart field into a string, refusing fields over `max_bytes`.
+/// Chunks are joined before decoding, so characters split across chunks survive.
+pub async fn read_text_field(field: &mut Field, max_bytes: usize) -> Result<String, UploadError> {
+    let mut value = Vec::new();
+    while let Some(chunk) = field.next().await {
+        let data = chunk?;
+        if value.len() + data.len() > max_bytes {
+            return Err(UploadError::Rejected("A form field is too long"));
+        }
+        value.extend_from_slice(&data);
+    }
+    Ok(String::from_utf8_lossy(&value).into_owned())
+}
+
+/// Validates and stores the `media` field of a post form.
+/// Accepts JPEG, PNG, GIF and WEBP images and MP4 videos, identified by their
+/// contents. The file name and content type sent by the browser only have to agree
+/// with what was found; the stored name and extension come from the contents.
+/// Files on the blocklist are refused, and a file identical to one already stored is
+/// not stored again. Returns `Ok(None)` when no file was chosen.
+pub async fn save_media_field(
+    field: &mut Field,
+    pool: &Pool<Postgres>,
+) -> Result<Option<SavedMedia>, UploadError> {
+    let filename = match field.content_disposition().get_filename() {
+        Some(filename) if !filename.trim().is_empty() => filename.to_string(),
+        _ => return Ok(None),
+    };
+
+    // Identify the file from its first bytes before anything is written to disk
+    let mut head = Vec::new();
+    while head.len() < sniff::SNIFF_LEN {
+        match field.next().await {
+            Some(chunk) => head.extend_from_slice(&chunk?),
+            None => break,
+        }
+    }
+    let kind = match MediaKind::detect(&head) {
+        Some(kind) => kind,
+        None => {
+            return Err(UploadError::Rejected(
+                "Unsupported file type. Images must be JPEG, PNG, GIF or WEBP, and videos MP4",
+            ))
+        }
+    };
+    if !claims_match(&filename, field.content_type(), kind) {
+        return Err(UploadError::Rejected(
+            "The file's contents do not match its name or type",
+        ));
+    }
+
+    let limits = &config::get().limits;
+    let sanitized_filename = format!("{}.{}", Uuid::new_v4(), kind.extension());
+    if !kind.is_image() {
+        let filepath = config::get().paths.video_uploads.join(&sanitized_filename);
+        write_field_to_file(field, &head, &filepath, limits.max_video_bytes).await?;
+        let path = filepath.clone();
+        blocking(move || trim_to_container(&path, kind)).await?;
+        let hash = match check_hash(pool, &filepath).await? {
+            (_, Some(stored)) => return Ok(Some(stored)),
+            (hash, None) => hash,
+        };
+
+        return Ok(Some(SavedMedia {
+            url: format!("/uploads/videos/{}", sanitized_filename),
+            media_type: "video".to_string(),
+            thumb_url: None,
+            width: None,
+            height: None,
+            size: std::fs::metadata(&filepath)?.len() as i64,
+            hash,
+            phash: None,
+            shared: false,
+        }));
+    }
+
+    let filepath = config::get().paths.image_uploads.join(&sanitized_filename);
+    write_field_to_file(field, &head, &filepath, limits.max_image_bytes).await?;
+    let path = filepath.clone();
+    blocking(move || trim_to_container(&path, kind)).await?;
+    let hash = match check_hash(pool, &filepath).await? {
+        (_, Some(mut stored)) => {
+            // Posts from before perceptual hashes were recorded are hashed from their file,
+            // so a copy of an image banned since it was first posted is still caught
+            let phash = match stored.phash {
+                Some(phash) => phash,
+                None => {
+                    let path = media_path(&stored.url)
+                        .ok_or(UploadError::Rejected("Invalid image file"))?;
+                    let phash = blocking(move || {
+                        hashes::perceptual_hash_of_file(&path)
+                            .ok_or(UploadError::Rejected("Invalid image file"))
+                    })
+                    .await?;
+                    hashes::record_phash(pool, &stored.hash, phash).await?;
+                    stored.phash = Some(phash);
+                    phash
+                }
+            };
+            if hashes::is_banned_image(pool, phash).await? {
+                return Err(UploadError::Rejected("This image is not allowed"));
+            }
+            return Ok(Some(stored));
+        }
+        (hash, None) => hash,
+    };
+
+    let path = filepath.clone();
+    let result = blocking(move || {
+        let (img, size) = process_image(&path, kind)?;
+        let phash = hashes::perceptual_hash(&img);
+        Ok((img, size, phash))
+    })
+    .await;
+    if result.is_err() {
+        std::fs::remove_file(&filepath).ok();
+    }
+    let (img, size, phash) = result?;
+
+    let banned = hashes::is_banned_image(pool, phash).await;
+    if !matches!(banned, Ok(false)) {
+        std::fs::remove_file(&filepath).ok();
+    }
+    if banned? {
+        return Err(UploadError::Rejected("This image is not allowed"));
+    }
+
+    let (width, height) = (img.width(), img.height());
+    // A missing thumbnail falls back to the full image, so it never fails the upload
+    let thumb_url = match blocking(move || Ok(create_thumbnail(&img))).await? {
+        Ok(url) => Some(url),
+        Err(e) => {
+            log::warn!("Failed to create thumbnail for {}: {}", filepath.display(), e);
+            None
+        }
+    };
+
+    Ok(Some(SavedMedia {
+        url: format!("/uploads/images/{}", sanitized_filename),
+        media_type: "image".to_string(),
+        thumb_url,
+        width: i32::try_from(width).ok(),
+        height: i32::try_from(height).ok(),
+        size,
+        hash,
+        phash: Some(phash),
+        shared: false,
+    }))
+}
+
+// Hashes a file just written to `filepath` and refuses it if it is on the blocklist.
+// When the same file is already stored for another post, the new copy is removed and
+// the stored one returned in its place.
+async fn check_hash(
+    pool: &Pool<Postgres>,
+    filepath: &Path,
+) -> Result<(String, Option<SavedMedia>), UploadError> {
+    let result = async {
+        let path = filepath.to_path_buf();
+        let hash = blocking(move || Ok(hashes::file_sha256(&path)?)).await?;
+        if hashes::is_banned(pool, &hash).await? {
+            return Err(UploadError::Rejected("This file is not allowed"));
+        }
+        let stored = hashes::find_stored(pool, &hash).await?;
+        Ok((hash, stored))
+    }
+    .await;
+    if !matches!(result, Ok((_, None))) {
+        std::fs::remove_file(filepath).ok();
+    }
+    result
+}
+
+// Runs decoding, encoding, hashing and other whole-file work on the blocking thread
+// pool, so a few large uploads cannot stall the workers serving every other request
+async fn blocking<T, F>(work: F) -> Result<T, UploadError>
+where
+    F: FnOnce() -> Result<T, UploadError> + Send + 'static,
+    T: Send + 'static,
+{
+    web::block(work).await.map_err(|e| UploadError::Internal(e.into()))?
+}
+
+// Decodes an image that has been written to `filepath`, turning it upright, and
+// unless `images.strip_metadata` is off, rewrites the file without its metadata.
+// Returns the upright image and the size of the file as finally stored.
+fn process_image(filepath: &Path, kind: MediaKind) -> Result<(DynamicImage, i64), UploadError> {
+    let config = config::get();
+
+    // Read the size from the header first, so a small file that decodes to an
+    // enormous image is refused before any pixels are allocated
+    let (width, height) = image::image_dimensions(filepath)
+        .map_err(|_| UploadError::Rejected("Invalid image file"))?;
+    if u64::from(width) * u64::from(height) > config.limits.max_image_pixels {
+        return Err(UploadError::Rejected("Image dimensions are too large"));
+    }
+
+    let data = std::fs::read(filepath)?;
+    let format = kind.image_format().ok_or(UploadError::Rejected("Invalid image file"))?;
+    let img = image::load_from_memory_with_format(&data, format)
+        .map_err(|_| UploadError::Rejected("Invalid image file"))?;
+    let orientation = metadata::exif_orientation(kind, &data);
+    let img = metadata::apply_orientation(img, orientation);
+
+    if config.images.strip_metadata {
+        // Animated GIFs and WEBPs would lose every frame but the first if re-encoded,
+        // so their metadata is cut out instead
+        let stripped = match kind {
+            MediaKind::Gif => metadata::strip_gif(&data),
+            MediaKind::Webp => metadata::strip_webp(&data, orientation),
+            _ => Some(encode_image(&img, kind, config.images.jpeg_quality).map_err(|e| {
+                UploadError::Internal(e.into())
+            })?),
+        };
+        let stripped = stripped.ok_or(UploadError::Rejected("Invalid image file"))?;
+        std::fs::write(filepath, stripped)?;
+    }
+
+    Ok((img, std::fs::metadata(filepath)?.len() as i64))
+}
+
+// Encodes an upright image as a fresh JPEG or PNG, which carries none of the original's metadata
+fn encode_image(
+    img: &DynamicImage,
+    kind: MediaKind,
+    jpeg_quality: u8,
+) -> Result<Vec<u8>, image::ImageError> {
+    let mut out = std::io::Cursor::new(Vec::new());
+    if kind == MediaKind::Jpeg {
+        let rgb = DynamicImage::ImageRgb8(img.to_rgb8());
+        rgb.write_to(&mut out, image::ImageOutputFormat::Jpeg(jpeg_quality))?;
+    } else {
+        img.write_to(&mut out, image::ImageOutputFormat::Png)?;
+    }
+    Ok(out.into_inner())
+}
+
+// Whether the file name's extension and the part's content type, when they name a
+// media type at all, both agree with the detected one
+fn claims_match(filename: &str, content_type: &mime::Mime, kind: MediaKind) -> bool {
+    let from_name = mime_guess::from_path(filename).first();
+    let from_part = Some(content_type)
+        .filter(|m| m.type_() == mime::IMAGE || m.type_() == mime::VIDEO)
+        .cloned();
+    from_name
+        .into_iter()
+        .chain(from_part)
+        .all(|claimed| claimed.essence_str() == kind.mime())
+}
+
+// Cuts off anything stored after the end of the image or video, such as an archive
+// appended to a JPEG, blanks MP4 boxes that are not needed for playback, and refuses
+// files whose structure does not hold together. The file is removed when it is refused.
+fn trim_to_container(filepath: &Path, kind: MediaKind) -> Result<(), UploadError> {
+    let result = container_len(filepath, kind).and_then(|end| match end {
+        Some(end) => {
+            let f = std::fs::OpenOptions::new().write(true).open(filepath)?;
+            if f.metadata()?.len() > end {
+                f.set_len(end)?;
+            }
+            Ok(())
+        }
+        None if kind.is_image() => Err(UploadError::Rejected("Invalid image file")),
+        None => Err(UploadError::Rejected("Invalid video file")),
+    });
+    if result.is_err() {
+        std::fs::remove_file(filepath).ok();
+    }
+    result
+}
+
+fn container_len(filepath: &Path, kind: MediaKind) -> Result<Option<u64>, UploadError> {
+    if kind.is_image() {
+        let data = std::fs::read(filepath)?;
+        Ok(sniff::image_len(kind, &data).map(|len| len as u64))
+    } else {
+        let mut file = std::fs::File::options().read(true).write(true).open(filepath)?;
+        Ok(sniff::scrub_mp4(&mut file)?)
+    }
+}
+
+// Streams a field to disk after the bytes already read from it (`head`), giving up as
+// soon as the total passes `max_bytes`. The partial file is removed whenever the upload
+// does not complete.
+async fn write_field_to_file(
+    field: &mut Field,
+    head: &[u8],
+    filepath: &Path,
+    max_bytes: u64,
+) -> Result<(), UploadError> {
+    let mut f = std::fs::File::create(filepath)?;
+    let mut written: u64 = 0;
+    let mut write = move |data: &[u8]| {
+        written += data.len() as u64;
+        if written > max_bytes {
+            return Err(UploadError::TooLarge(max_bytes));
+        }
+        f.write_all(data).map_err(UploadError::from)
+    };
+
+    let mut result = write(head);
+    while result.is_ok() {
+        match field.next().await {
+            Some(chunk) => result = chunk.map_err(UploadError::from).and_then(|data| write(&data)),
+            None => break,
+        }
+    }
+    drop(write);
+    if result.is_err() {
+        std::fs::remove_file(filepath).ok();
+    }
+    result
+}
+
+/// Writes a thumbnail for a decoded image into the thumbnail directory and returns its URL.
+/// The aspect ratio is preserved and small images are never upscaled.
+/// Animated GIFs decode to their first frame. Images with transparency are saved
+/// as PNG, everything else as JPEG.
+pub fn create_thumbnail(img: &image::DynamicImage) -> Result<String, image::ImageError> {
+    let max = config::get().images.thumb_max_dimension;
+    let thumb = if img.width() > max || img.height() > max {
+        img.thumbnail(max, max)
+    } else {
+        img.clone()
+    };
+
+    let thumb_name = if thumb.color().has_alpha() {
+        let name = format!("{}.png", Uuid::new_v4());
+        thumb.save_with_format(
+            config::get().paths.image_thumbs.join(&name),
+            image::ImageFormat::Png,
+        )?;
+        name
+    } else {
+        let name = format!("{}.jpg", Uuid::new_v4());
+        image::DynamicImage::ImageRgb8(thumb.to_rgb8()).save_with_format(
+            config::get().paths.image_thumbs.join(&name),
+            image::ImageFormat::Jpeg,
+        )?;
+        name
+    };
+
+    Ok(format!("/thumbs/images/{}", thumb_name))
+}
+
+/// Maps a public media URL such as `/uploads/images/<file>` back to its path on disk.
+/// Returns `None` for anything that is not a plain file name inside an upload or thumbnail directory.
+pub fn media_path(url: &str) -> Option<PathBuf> {
+    let paths = &config::get().paths;
+    let (dir, name) = if let Some(name) = url.strip_prefix("/uploads/images/") {
+        (&paths.image_uploads, name)
+    } else if let Some(name) = url.strip_prefix("/uploads/videos/") {
+        (&paths.video_uploads, name)
+    } else if let Some(name) = url.strip_prefix("/thumbs/images/") {
+        (&paths.image_thumbs, name)
+    } else {
+        return None;
+    };
+
+    if name.is_empty() || name.contains('/') || name.contains("..") {
+        return None;
+    }
+    Some(dir.join(name))
+}
+
+/// Removes an uploaded file that will not be attached to a post after all.
+/// Files shared with earlier posts are left alone.
+pub fn discard(media: Option<SavedMedia>) {
+    if let Some(media) = media.filter(|media| !media.shared) {
+        for url in std::iter::once(&media.url).chain(media.thumb_url.as_ref()) {
+            if let Some(path) = media_path(url) {
+                std::fs::remove_file(path).ok();
+            }
+        }
+    }
+}
@@ -4,8 +4,14 @@ document.addEventListener('DOMContentLoaded', () => {
     const images = document.querySelectorAll('.toggle-image');
 
     images.forEach(img => {
-        img.addEventListener('click', () => {
-            img.classList.toggle('expanded');
+        img.addEventListener('click', event => {
+            // Swap between the thumbnail and the full-size upload
+            event.preventDefault();
+            const expanded = img.classList.toggle('expanded');
+            const src = expanded ? img.dataset.full : img.dataset.thumb;
+            if (src) {
+                img.src = src;
+            }
         });
     });
 });